@@ -2,22 +2,35 @@ extern crate ada;
 
 use minifb::{Key, Window, WindowOptions};
 
-use ada::{shape, Canvas};
+use ada::{shape, Canvas, PixelFormat};
 
 const WIDTH: usize = 512;
 const HEIGHT: usize = 512;
 
 
-fn draw_rectangle(canvas: &mut Canvas, buffer: &mut [u8]) {
-    shape::draw_rect2d_filled(50, 100, 100, 150, canvas, &ada::color::RED, buffer);
+fn draw_rectangle(canvas: &mut Canvas) {
+    shape::draw_rect2d_filled(50, 100, 100, 150, canvas, &ada::color::RED);
 }
 
-fn draw_circle(canvas: &mut Canvas, buffer: &mut [u8]) {
-    shape::draw_ellipse2d_filled(350, 200, 100, 100, canvas, &ada::color::BLUE, buffer);
+fn draw_circle(canvas: &mut Canvas) {
+    shape::draw_ellipse2d_filled(350, 200, 100, 100, canvas, &ada::color::BLUE);
 }
 
-fn draw_ellipse(canvas: &mut Canvas, buffer: &mut [u8]) {
-    shape::draw_ellipse2d_filled(150, 400, 100, 50, canvas, &ada::color::GREEN, buffer);
+fn draw_ellipse(canvas: &mut Canvas) {
+    shape::draw_ellipse2d_filled(150, 400, 100, 50, canvas, &ada::color::GREEN);
+}
+
+fn draw_polygon(canvas: &mut Canvas) {
+    let xi: [i32; 10] = [400, 418, 476, 429, 447, 400, 353, 371, 324, 382];
+    let yi: [i32; 10] = [330, 374, 374, 402, 456, 422, 456, 402, 374, 374];
+
+    shape::draw_polygon2d_filled(
+        &xi,
+        &yi,
+        shape::FillRule::NonZero,
+        canvas,
+        &ada::Color::rgb(255, 255, 0),
+    );
 }
 
 fn main() {
@@ -39,22 +52,17 @@ fn main() {
     // Limit to max ~30 fps update rate
     window.limit_update_rate(Some(std::time::Duration::from_micros(33333)));
 
-    // create canvas
-    let mut cbuffer: Vec<u8> = vec![0; 4 * WIDTH * HEIGHT];
-    let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
-
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        draw_rectangle(&mut canvas, &mut cbuffer[..]);
-        draw_circle(&mut canvas, &mut cbuffer[..]);
-        draw_ellipse(&mut canvas, &mut cbuffer[..]);
+        // create canvas, drawing straight into the packed 0RGB pixels of minifb
+        let mut canvas =
+            Canvas::from_words(WIDTH, HEIGHT, PixelFormat::Xrgb8888, &mut buffer).unwrap();
+        canvas.clear(&ada::color::BLACK);
 
-        for (i, pix) in buffer.iter_mut().enumerate() {
-            let c = canvas.get_color((i % WIDTH) as i32, (i / WIDTH) as i32,  &mut cbuffer[..]);
-            *pix = ((c[0] as u32) << 16) | ((c[1] as u32) << 8) | (c[2] as u32);
-        }
+        draw_rectangle(&mut canvas);
+        draw_circle(&mut canvas);
+        draw_ellipse(&mut canvas);
+        draw_polygon(&mut canvas);
 
         window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
-
-        canvas.clear(&ada::color::BLACK,  &mut cbuffer[..]);
     }
 }
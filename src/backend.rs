@@ -0,0 +1,87 @@
+use crate::shape::Path2D;
+use crate::Color;
+
+/// Storage of the pixels a [`Canvas`](crate::Canvas) draws into, for
+/// displays, recorders or surfaces which are not a plain buffer.
+///
+/// The canvas rasterizes the shapes, clipping them to `width x height` and
+/// compositing the colors, and hands the resulting pixels and horizontal
+/// spans to the backend. Coordinates are in pixels, with `(0, 0)` at the top
+/// left corner, and are always inside the backend.
+///
+/// Only the pixel accessors are required, the spans default to going pixel
+/// by pixel. Backends able to draw lines, ellipses and paths themselves can
+/// also take them whole through [`line`](Backend::line),
+/// [`ellipse`](Backend::ellipse) and [`path`](Backend::path), the canvas
+/// rasterizing the ones they leave. Targets wanting only the shapes
+/// implement [`Target`](crate::shape::Target) instead.
+///
+/// The shapes are handed over only when drawing them takes nothing but
+/// their geometry in pixels, the line width and the color: the canvas
+/// covers the whole backend without a clip, composites colors over the
+/// pixels with the normal blend mode and strokes solid outlines with the
+/// default caps, joins and miter limit.
+///
+/// ```ignore
+/// let mut display = Display::open()?;
+/// let mut canvas = Canvas::from_backend(&mut display);
+/// shape::draw_line2d(0, 0, 100, 50, &mut canvas, &color::WHITE);
+/// ```
+pub trait Backend {
+    /// Width in pixels
+    fn width(&self) -> usize;
+
+    /// Height in pixels
+    fn height(&self) -> usize;
+
+    /// Color of the pixel at `(x, y)`, read to composite colors over it
+    fn get_pixel(&self, x: usize, y: usize) -> Color;
+
+    /// Replaces the pixel at `(x, y)` with the color
+    fn set_pixel(&mut self, x: usize, y: usize, color: &Color);
+
+    /// Replaces the `len` pixels of row `y` starting at `x` with the color
+    fn fill_span(&mut self, x: usize, y: usize, len: usize, color: &Color) {
+        for x in x..x + len {
+            self.set_pixel(x, y, color);
+        }
+    }
+
+    /// Replaces each of the `len` pixels of row `y` starting at `x` with the
+    /// color returned by `blend` for it
+    fn blend_span(&mut self, x: usize, y: usize, len: usize, blend: &dyn Fn(&Color) -> Color) {
+        for x in x..x + len {
+            let color = blend(&self.get_pixel(x, y));
+            self.set_pixel(x, y, &color);
+        }
+    }
+
+    /// Draws the line from `from` to `to`, `width` pixels wide, returning
+    /// whether it did. Unhandled by default.
+    fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, color: &Color) -> bool {
+        let _ = (from, to, width, color);
+        false
+    }
+
+    /// Draws the axis aligned ellipse, filled or as an outline `width`
+    /// pixels wide, returning whether it did. Unhandled by default.
+    fn ellipse(
+        &mut self,
+        center: (f32, f32),
+        radii: (f32, f32),
+        filled: bool,
+        width: f32,
+        color: &Color,
+    ) -> bool {
+        let _ = (center, radii, filled, width, color);
+        false
+    }
+
+    /// Draws the path, given in pixels, filled according to its fill rule or
+    /// stroked `width` pixels wide, returning whether it did. Unhandled by
+    /// default.
+    fn path(&mut self, path: &Path2D, filled: bool, width: f32, color: &Color) -> bool {
+        let _ = (path, filled, width, color);
+        false
+    }
+}
@@ -1,81 +1,1769 @@
+use crate::backend::Backend;
+use crate::composite::{composite, BlendMode, CompositeMode};
+use crate::errors::unsupported_error;
+use crate::pixel::PixelFormat;
+use crate::shape::{LineCap, LineJoin, Stroke};
+use crate::transform::Transform;
 use crate::{shape, Color, Result};
 
+/// Inclusive rectangle `(x_min, y_min, x_max, y_max)`
+type Rect = (i32, i32, i32, i32);
+
+/// Mapping from the coordinates of the shapes to pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Mapping {
+    /// transform applied to the shapes before the viewport
+    transform: Transform,
+    /// position in pixels of the origin
+    origin: (f32, f32),
+    /// whether the y axis points up
+    y_up: bool,
+    /// scale of the viewport
+    scale: (f32, f32),
+    /// translation of the viewport, applied after scaling
+    translation: (f32, f32),
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Mapping {
+            transform: Transform::identity(),
+            origin: (0.0, 0.0),
+            y_up: false,
+            scale: (1.0, 1.0),
+            translation: (0.0, 0.0),
+        }
+    }
+}
+
+impl Mapping {
+    #[inline(always)]
+    fn is_identity(&self) -> bool {
+        *self == Mapping::default()
+    }
+
+    /// Maps the point to pixels, in double precision so integer coordinates
+    /// stay exact
+    #[inline]
+    fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x, y) = self.transform.apply_f64(x, y);
+        let x = x * self.scale.0 as f64 + self.translation.0 as f64;
+        let y = y * self.scale.1 as f64 + self.translation.1 as f64;
+        let y = if self.y_up { -y } else { y };
+        (self.origin.0 as f64 + x, self.origin.1 as f64 + y)
+    }
+}
+
+/// Pixels of a canvas, either owned or borrowed from the caller
+enum Storage<'a> {
+    /// buffer allocated by the canvas
+    Owned(Vec<u8>),
+    /// borrowed byte buffer
+    Bytes(&'a mut [u8]),
+    /// borrowed buffer of packed 32 bit pixels
+    Words(&'a mut [u32]),
+    /// pixels handed to a backend
+    Backend(&'a mut dyn Backend),
+}
+
 /// Container for drawing the shapes
-pub struct Canvas {
+///
+/// The canvas owns or borrows the pixel buffer it draws into, its size being
+/// checked once when the canvas is created.
+///
+/// Only the pixels inside `[0, width) x [0, height)` belong to the canvas,
+/// anything drawn outside of it is clipped.
+pub struct Canvas<'a> {
     /// height of canvas
     height: usize,
     /// width of canvas
     width: usize,
-
-    h_minus_1: usize,
-    w_minus_1: usize,
+    /// layout of the pixels in the buffer
+    format: PixelFormat,
+    /// number of bytes from the start of a row to the start of the next one
+    stride: usize,
+    /// pixel buffer
+    storage: Storage<'a>,
+    /// position in the buffer of the local origin of the canvas
+    offset: (i32, i32),
+    /// mapping from the coordinates of the shapes to pixels
+    mapping: Mapping,
+    /// inclusive rectangles in buffer coordinates, each one inside the
+    /// previous. The first is the area of the buffer the canvas can draw on,
+    /// the last one clips the shapes.
+    clips: Vec<Rect>,
+    /// whether outlines are drawn anti-aliased
+    antialias: bool,
+    /// how drawn colors are combined with the buffer
+    composite_mode: CompositeMode,
+    /// how drawn colors are mixed with the buffer before compositing
+    blend_mode: BlendMode,
+    /// style used for outlines
+    stroke: Stroke,
 }
 
-impl Canvas {
-    /// creates a new canvas. Takes:
+impl Canvas<'static> {
+    /// creates a new canvas with its own buffer of RGBA pixels, initially
+    /// transparent black. Takes:
     ///
     /// ```ignore
     /// width: width of the canvas
     /// height: height of the canvas
     /// ```
+    pub fn new(width: usize, height: usize) -> Result<Canvas<'static>> {
+        Canvas::with_format(width, height, PixelFormat::Rgba8888)
+    }
+
+    /// creates a new canvas with its own buffer of pixels laid out in the
+    /// given format.
     ///
-    /// Canvas does not create a internal buffer, it takes a buffer during draw
-    pub fn new(width: usize, height: usize) -> Result<Canvas> {
-        Ok(Canvas {
-            width: width,
-            height: height,
-            w_minus_1: width - 1,
-            h_minus_1: height - 1,
-        })
+    /// Colors are converted to the format when drawn and back when read.
+    pub fn with_format(
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+    ) -> Result<Canvas<'static>> {
+        let size = match buffer_size(width, height, format.bytes_per_pixel()) {
+            Some(size) => size,
+            None => return unsupported_error("canvas is too large"),
+        };
+
+        Ok(Canvas::build(
+            width,
+            height,
+            format,
+            width * format.bytes_per_pixel(),
+            Storage::Owned(vec![0; size]),
+        ))
     }
+}
 
-    /// Draw the shape within the bounds in the canvas
+impl<'a> Canvas<'a> {
+    /// creates a new canvas drawing into the given buffer, whose pixels are
+    /// laid out in `format` one row after the other.
     ///
-    /// Provide a mutable buffer to fill the values. It assumes color mode to
-    /// RGBA.
-    pub fn draw(&mut self, shape: &dyn shape::Shape, color: &Color, buffer: &mut [u8]) {
+    /// Fails if the buffer does not hold exactly
+    /// `width * height * format.bytes_per_pixel()` bytes.
+    pub fn from_bytes(
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        buffer: &'a mut [u8],
+    ) -> Result<Canvas<'a>> {
+        if buffer_size(width, height, format.bytes_per_pixel()) != Some(buffer.len()) {
+            return unsupported_error("buffer size does not match the canvas");
+        }
+
+        let stride = width * format.bytes_per_pixel();
+        Ok(Canvas::build(
+            width,
+            height,
+            format,
+            stride,
+            Storage::Bytes(buffer),
+        ))
+    }
+
+    /// creates a new canvas drawing into a buffer whose rows are `stride`
+    /// bytes apart, like padded framebuffers or a region of a larger surface.
+    ///
+    /// Bytes past the last pixel of each row are never touched, so drawing
+    /// into a sub-region of a surface only needs the buffer to start at the
+    /// first pixel of the region. Fails if a row of pixels does not fit in
+    /// the stride, or if the buffer is shorter than
+    /// `stride * (height - 1) + width * format.bytes_per_pixel()` bytes.
+    pub fn from_bytes_with_stride(
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        stride: usize,
+        buffer: &'a mut [u8],
+    ) -> Result<Canvas<'a>> {
+        let row = match width.checked_mul(format.bytes_per_pixel()) {
+            Some(row) => row,
+            None => return unsupported_error("canvas is too large"),
+        };
+        if stride < row {
+            return unsupported_error("stride is smaller than a row of pixels");
+        }
+        let size = match height {
+            0 => Some(0),
+            _ => stride
+                .checked_mul(height - 1)
+                .and_then(|size| size.checked_add(row)),
+        };
+        if !size.is_some_and(|size| buffer.len() >= size) {
+            return unsupported_error("buffer is too small for the canvas");
+        }
+
+        Ok(Canvas::build(
+            width,
+            height,
+            format,
+            stride,
+            Storage::Bytes(buffer),
+        ))
+    }
+
+    /// creates a new canvas drawing into a buffer of packed 32 bit pixels,
+    /// like the framebuffers of most windowing libraries.
+    ///
+    /// Fails if the pixels of `format` do not take 4 bytes, or if the buffer
+    /// does not hold exactly `width * height` pixels.
+    pub fn from_words(
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        buffer: &'a mut [u32],
+    ) -> Result<Canvas<'a>> {
+        if format.bytes_per_pixel() != 4 {
+            return unsupported_error("pixel format does not fit in 32 bit words");
+        }
+        if buffer_size(width, height, 1) != Some(buffer.len()) {
+            return unsupported_error("buffer size does not match the canvas");
+        }
+
+        Ok(Canvas::build(
+            width,
+            height,
+            format,
+            width * 4,
+            Storage::Words(buffer),
+        ))
+    }
+
+    /// creates a new canvas drawing through the backend, which receives the
+    /// pixels and spans of the shapes. The canvas takes the size of the
+    /// backend.
+    ///
+    /// The canvas has no buffer of its own, [`bytes`](Canvas::bytes) and
+    /// [`words`](Canvas::words) return `None` and colors are passed to the
+    /// backend unconverted.
+    pub fn from_backend(backend: &'a mut dyn Backend) -> Canvas<'a> {
+        let (width, height) = (backend.width(), backend.height());
+        let format = PixelFormat::Rgba8888;
+        Canvas::build(
+            width,
+            height,
+            format,
+            width * format.bytes_per_pixel(),
+            Storage::Backend(backend),
+        )
+    }
+
+    fn build(
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        stride: usize,
+        storage: Storage<'a>,
+    ) -> Canvas<'a> {
+        let bounds = (
+            0,
+            0,
+            width.min(i32::MAX as usize) as i32 - 1,
+            height.min(i32::MAX as usize) as i32 - 1,
+        );
+        Canvas {
+            width,
+            height,
+            format,
+            stride,
+            storage,
+            offset: (0, 0),
+            mapping: Mapping::default(),
+            clips: vec![bounds],
+            antialias: false,
+            composite_mode: CompositeMode::default(),
+            blend_mode: BlendMode::default(),
+            stroke: Stroke::default(),
+        }
+    }
+
+    /// Creates a view of the rectangle at `(x, y)` of size `width x height`
+    /// of the canvas. The view has its own local origin at the top left
+    /// corner of the rectangle and draws into the same buffer, but never
+    /// outside of the rectangle nor of the current clip of this canvas.
+    ///
+    /// The rectangle is given in pixels of this canvas, regardless of its
+    /// coordinate mapping. The view starts with the drawing settings of this
+    /// canvas and an identity mapping, changing them on the view does not
+    /// affect this canvas.
+    pub fn sub_canvas(&mut self, x: i32, y: i32, width: usize, height: usize) -> Canvas<'_> {
+        let offset = (
+            self.offset.0.saturating_add(x),
+            self.offset.1.saturating_add(y),
+        );
+        let rect = (
+            offset.0,
+            offset.1,
+            (offset.0 as i64 + width as i64 - 1).min(i32::MAX as i64) as i32,
+            (offset.1 as i64 + height as i64 - 1).min(i32::MAX as i64) as i32,
+        );
+        let bounds = intersect(self.clip(), rect);
+
+        let storage = match &mut self.storage {
+            Storage::Owned(buffer) => Storage::Bytes(&mut buffer[..]),
+            Storage::Bytes(buffer) => Storage::Bytes(&mut buffer[..]),
+            Storage::Words(buffer) => Storage::Words(&mut buffer[..]),
+            Storage::Backend(backend) => Storage::Backend(&mut **backend),
+        };
+        Canvas {
+            width,
+            height,
+            format: self.format,
+            stride: self.stride,
+            storage,
+            offset,
+            mapping: Mapping::default(),
+            clips: vec![bounds],
+            antialias: self.antialias,
+            composite_mode: self.composite_mode,
+            blend_mode: self.blend_mode,
+            stroke: self.stroke.clone(),
+        }
+    }
+
+    /// Restricts drawing to the rectangle at `(x, y)` of size
+    /// `width x height`, intersected with the current clip, until the
+    /// matching [`pop_clip`](Canvas::pop_clip). The rectangle is given in
+    /// pixels, regardless of the coordinate mapping.
+    pub fn push_clip(&mut self, x: i32, y: i32, width: usize, height: usize) {
+        let (x, y) = (
+            self.offset.0.saturating_add(x),
+            self.offset.1.saturating_add(y),
+        );
+        let rect = (
+            x,
+            y,
+            (x as i64 + width as i64 - 1).min(i32::MAX as i64) as i32,
+            (y as i64 + height as i64 - 1).min(i32::MAX as i64) as i32,
+        );
+        let clip = intersect(self.clip(), rect);
+        self.clips.push(clip);
+    }
+
+    /// Removes the clip rectangle pushed last, returning `false` if there was
+    /// none.
+    pub fn pop_clip(&mut self) -> bool {
+        if self.clips.len() > 1 {
+            self.clips.pop();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Sets the position in pixels of the origin of the coordinates the
+    /// shapes are given in. Defaults to `(0.0, 0.0)`, the top left corner.
+    pub fn set_origin(&mut self, x: f32, y: f32) {
+        self.mapping.origin = (x, y);
+    }
+
+    /// Position in pixels of the origin of the shape coordinates
+    pub fn origin(&self) -> (f32, f32) {
+        self.mapping.origin
+    }
+
+    /// Makes the y axis of the shape coordinates point up instead of down,
+    /// as in math and plots. Disabled by default.
+    pub fn set_y_up(&mut self, y_up: bool) {
+        self.mapping.y_up = y_up;
+    }
+
+    /// Whether the y axis of the shape coordinates points up
+    pub fn is_y_up(&self) -> bool {
+        self.mapping.y_up
+    }
+
+    /// Sets the viewport mapping shape coordinates to pixels. Coordinates
+    /// are scaled by `scale`, then moved by `translation` and then placed
+    /// relative to the origin. Defaults to a scale of `(1.0, 1.0)` and no
+    /// translation.
+    ///
+    /// Radii of ellipses are scaled too, while line widths and dash patterns
+    /// stay in pixels.
+    pub fn set_viewport(&mut self, scale: (f32, f32), translation: (f32, f32)) {
+        self.mapping.scale = scale;
+        self.mapping.translation = translation;
+    }
+
+    /// Scale and translation of the viewport
+    pub fn viewport(&self) -> ((f32, f32), (f32, f32)) {
+        (self.mapping.scale, self.mapping.translation)
+    }
+
+    /// Sets the transform the shapes are drawn through, applied before the
+    /// viewport. Defaults to the identity.
+    ///
+    /// Rectangles and ellipses which are rotated or skewed by it are drawn
+    /// as polygons approximating them.
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.mapping.transform = transform;
+    }
+
+    /// Transform the shapes are drawn through
+    pub fn transform(&self) -> Transform {
+        self.mapping.transform
+    }
+
+    /// Maps a point from shape coordinates to pixels of the canvas, pixel
+    /// centers lying at integer positions.
+    pub fn to_pixel(&self, x: f32, y: f32) -> (f32, f32) {
+        let (x, y) = self.mapping.apply(x as f64, y as f64);
+        (x as f32, y as f32)
+    }
+
+    /// Width of the canvas in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the canvas in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Bytes of the pixel buffer, or `None` if the canvas draws into packed
+    /// 32 bit pixels or a backend.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.storage {
+            Storage::Owned(buffer) => Some(buffer),
+            Storage::Bytes(buffer) => Some(buffer),
+            Storage::Words(_) | Storage::Backend(_) => None,
+        }
+    }
+
+    /// Packed 32 bit pixels of the buffer, or `None` if the canvas draws into
+    /// bytes or a backend.
+    pub fn words(&self) -> Option<&[u32]> {
+        match &self.storage {
+            Storage::Words(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    /// Layout of the pixels in the buffer
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Number of bytes between the starts of two consecutive rows
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Sets how colors of the shapes drawn next are combined with the pixels
+    /// already in the buffer. Defaults to [`CompositeMode::SourceOver`].
+    pub fn set_composite_mode(&mut self, mode: CompositeMode) {
+        self.composite_mode = mode;
+    }
+
+    /// Current compositing mode of the canvas
+    pub fn composite_mode(&self) -> CompositeMode {
+        self.composite_mode
+    }
+
+    /// Sets how colors of the shapes drawn next are mixed with the pixels
+    /// already in the buffer, before being composited. Defaults to
+    /// [`BlendMode::Normal`].
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    /// Current blend mode of the canvas
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Enables or disables anti-aliasing of lines, outlines and ellipses
+    /// drawn on the canvas. It is disabled by default.
+    pub fn set_antialias(&mut self, antialias: bool) {
+        self.antialias = antialias;
+    }
+
+    /// Whether lines, outlines and ellipses are drawn anti-aliased
+    pub fn is_antialiased(&self) -> bool {
+        self.antialias
+    }
+
+    /// Sets the width in pixels of the outlines drawn next. Defaults to `1.0`.
+    ///
+    /// Outlines one pixel wide or thinner are drawn with the line algorithms,
+    /// wider ones are filled as polygons centered on the outline. Negative
+    /// widths are taken as zero and non finite ones are ignored.
+    pub fn set_line_width(&mut self, width: f32) {
+        if width.is_finite() {
+            self.stroke.width = width.max(0.0);
+        }
+    }
+
+    /// Width in pixels of the outlines
+    pub fn line_width(&self) -> f32 {
+        self.stroke.width
+    }
+
+    /// Sets the shape of the ends of open outlines wider than a pixel.
+    /// Defaults to [`LineCap::Butt`].
+    pub fn set_line_cap(&mut self, cap: LineCap) {
+        self.stroke.cap = cap;
+    }
+
+    /// Shape of the ends of open outlines
+    pub fn line_cap(&self) -> LineCap {
+        self.stroke.cap
+    }
+
+    /// Sets the shape of the corners of outlines wider than a pixel.
+    /// Defaults to [`LineJoin::Miter`].
+    pub fn set_line_join(&mut self, join: LineJoin) {
+        self.stroke.join = join;
+    }
+
+    /// Shape of the corners of outlines
+    pub fn line_join(&self) -> LineJoin {
+        self.stroke.join
+    }
+
+    /// Sets the longest miter allowed, as a ratio of the miter length to the
+    /// line width. Sharper corners are drawn beveled. Defaults to `10.0`.
+    ///
+    /// Limits which are not positive and finite are ignored.
+    pub fn set_miter_limit(&mut self, limit: f32) {
+        if limit.is_finite() && limit > 0.0 {
+            self.stroke.miter_limit = limit;
+        }
+    }
+
+    /// Longest miter allowed, as a ratio of miter length to line width
+    pub fn miter_limit(&self) -> f32 {
+        self.stroke.miter_limit
+    }
+
+    /// Sets the dash pattern of the outlines drawn next, as alternating
+    /// lengths in pixels of dashes and gaps, starting with a dash. An empty
+    /// pattern draws solid outlines, which is the default.
+    ///
+    /// A pattern with an odd number of entries is repeated to make it even.
+    /// Patterns with negative or non finite lengths, or adding up to zero,
+    /// are ignored.
+    ///
+    /// The pattern is measured along the whole outline of a shape, so it
+    /// continues across the corners of rectangles, polygons and the flattened
+    /// segments of curves. Zero length dashes with round or square caps can
+    /// be used to draw dotted outlines.
+    pub fn set_line_dash(&mut self, pattern: &[f32]) {
+        let valid = pattern.iter().all(|l| l.is_finite() && *l >= 0.0);
+        if !valid || (!pattern.is_empty() && pattern.iter().sum::<f32>() <= 0.0) {
+            return;
+        }
+
+        self.stroke.dash = pattern.to_vec();
+        if !pattern.len().is_multiple_of(2) {
+            self.stroke.dash.extend_from_slice(pattern);
+        }
+    }
+
+    /// Dash pattern of the outlines, empty if they are solid
+    pub fn line_dash(&self) -> &[f32] {
+        &self.stroke.dash
+    }
+
+    /// Sets how far into the dash pattern outlines start. Defaults to `0.0`.
+    pub fn set_line_dash_offset(&mut self, offset: f32) {
+        if offset.is_finite() {
+            self.stroke.dash_offset = offset;
+        }
+    }
+
+    /// Distance into the dash pattern at which outlines start
+    pub fn line_dash_offset(&self) -> f32 {
+        self.stroke.dash_offset
+    }
+
+    /// Stroke style used for outlines
+    #[inline]
+    pub(crate) fn stroke(&self) -> &Stroke {
+        &self.stroke
+    }
+
+    /// Replaces the whole stroke style
+    #[inline]
+    pub(crate) fn set_stroke(&mut self, stroke: Stroke) {
+        self.stroke = stroke;
+    }
+
+    /// Draw the shape within the bounds in the canvas
+    pub fn draw(&mut self, shape: &dyn shape::Shape, color: &Color) {
         if shape.is_filled() {
-            shape.draw_filled(self, color, buffer)
+            shape.draw_filled(self, color)
         } else {
-            shape.draw(self, color, buffer)
+            shape.draw(self, color)
+        }
+    }
+
+    /// Draws the shapes in order with their colors, like calling
+    /// [`draw`](Canvas::draw) for each of them, splitting the canvas into
+    /// bands of rows which are rasterized in parallel, one per available
+    /// thread. The pixels are exactly the ones drawn serially.
+    ///
+    /// Canvases drawing into a [`Backend`] draw the shapes serially.
+    #[cfg(feature = "parallel")]
+    pub fn draw_parallel(&mut self, shapes: &[(&(dyn shape::Shape + Sync), Color)]) {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        self.draw_in_bands(shapes, threads);
+    }
+
+    /// Draws the shapes into at most `bands` bands of rows of the current
+    /// clip, each band on its own thread
+    #[cfg(feature = "parallel")]
+    fn draw_in_bands(&mut self, shapes: &[(&(dyn shape::Shape + Sync), Color)], bands: usize) {
+        let clip = self.clip();
+        let (_, y_min, _, y_max) = clip;
+        let rows = (y_max as i64 - y_min as i64 + 1).max(0) as usize;
+        let band_rows = rows.div_ceil(bands.max(1)).max(1);
+        if bands <= 1 || rows <= band_rows || matches!(self.storage, Storage::Backend(_)) {
+            for (shape, color) in shapes.iter() {
+                self.draw(*shape, color);
+            }
+            return;
         }
+
+        let settings = Settings {
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            stride: self.stride,
+            offset: self.offset,
+            clip,
+            mapping: self.mapping,
+            antialias: self.antialias,
+            composite_mode: self.composite_mode,
+            blend_mode: self.blend_mode,
+            stroke: self.stroke.clone(),
+        };
+        // the buffer is split at the first row of the clip, then into bands
+        let first = y_min as usize;
+        let (stride, band_len) = (self.stride, band_rows * self.stride);
+        let parts: Vec<Band> = match &mut self.storage {
+            Storage::Owned(buffer) => buffer[first * stride..]
+                .chunks_mut(band_len)
+                .map(Band::Bytes)
+                .collect(),
+            Storage::Bytes(buffer) => buffer[first * stride..]
+                .chunks_mut(band_len)
+                .map(Band::Bytes)
+                .collect(),
+            Storage::Words(buffer) => buffer[first * stride / 4..]
+                .chunks_mut(band_len / 4)
+                .map(Band::Words)
+                .collect(),
+            Storage::Backend(_) => unreachable!(),
+        };
+
+        let settings = &settings;
+        std::thread::scope(|scope| {
+            for (i, band) in parts.into_iter().take(bands).enumerate() {
+                let y_start = first + i * band_rows;
+                scope.spawn(move || {
+                    let mut canvas = settings.band(band, y_start, band_rows);
+                    for (shape, color) in shapes.iter() {
+                        canvas.draw(*shape, color);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Draw the shape through `transform`, applied before the transform of
+    /// the canvas
+    pub fn draw_transformed(
+        &mut self,
+        shape: &dyn shape::Shape,
+        transform: &Transform,
+        color: &Color,
+    ) {
+        let current = self.mapping.transform;
+        self.mapping.transform = current.compose(transform);
+        self.draw(shape, color);
+        self.mapping.transform = current;
     }
 
     /// fill the canvas buffer with specified color
-    pub fn clear(&mut self, color: &Color, buffer: &mut [u8]) {
-        for i in 0..self.width {
-            for j in 0..self.height {
-                self.draw_point_internal(i, j, color, buffer);
+    ///
+    /// The color replaces the pixels inside the current clip, irrespective of
+    /// the compositing mode.
+    pub fn clear(&mut self, color: &Color) {
+        let (x_min, y_min, x_max, y_max) = self.clip();
+        if x_min > x_max || y_min > y_max {
+            return;
+        }
+        let (x, len) = (x_min as usize, (x_max - x_min) as usize + 1);
+        let rows = y_min as usize..=y_max as usize;
+
+        // encode one row of pixels and copy it over the others
+        let bpp = self.format.bytes_per_pixel();
+        let mut pixel = [0u8; 4];
+        self.format.write(&mut pixel[..bpp], color);
+        let stride = self.stride;
+        let fill = |buffer: &mut [u8]| {
+            let row = pixel[..bpp].repeat(len);
+            for y in rows.clone() {
+                let si = x * bpp + y * stride;
+                buffer[si..si + row.len()].copy_from_slice(&row);
+            }
+        };
+        match &mut self.storage {
+            Storage::Owned(buffer) => fill(buffer),
+            Storage::Bytes(buffer) => fill(buffer),
+            Storage::Words(buffer) => {
+                let row = vec![u32::from_ne_bytes(pixel); len];
+                for y in rows {
+                    let si = x + y * stride / 4;
+                    buffer[si..si + len].copy_from_slice(&row);
+                }
+            }
+            Storage::Backend(backend) => {
+                for y in rows {
+                    backend.fill_span(x, y, len, color);
+                }
             }
         }
     }
 
-    /// Get the pixel's color value at specified coordinate
+    /// Get the pixel's color value at specified coordinate, converted from the
+    /// pixel format of the canvas
+    ///
+    /// The coordinate is mapped to the nearest pixel like the shapes are.
+    /// Returns `None` if it lies outside the canvas.
     #[inline]
-    pub fn get_color<'a>(&self, x: i32, y: i32, buffer: &'a mut [u8]) -> &'a [u8] {
-        // TODO: take care of mapping from user's coordinate plane to canvas
-        // Use origin shifting
-        let si = (x as usize + y as usize * self.width) * 4;
+    pub fn get_color(&self, x: i32, y: i32) -> Option<Color> {
+        let (x, y) = self.map_point(x, y);
+        let (x, y) = (
+            x as i64 + self.offset.0 as i64,
+            y as i64 + self.offset.1 as i64,
+        );
+        let (x_min, y_min, x_max, y_max) = self.clips[0];
+        if x < x_min as i64 || x > x_max as i64 || y < y_min as i64 || y > y_max as i64 {
+            return None;
+        }
 
-        return &(buffer[si..si + 4]);
+        Some(self.read_point_internal(x as usize, y as usize))
     }
 
+    /// Returns the inclusive bounds `(x_min, y_min, x_max, y_max)` of the
+    /// drawable area in local coordinates. Rasterizers use it to clip shapes
+    /// analytically.
+    ///
+    /// For an empty canvas `x_max < x_min` or `y_max < y_min`.
+    #[inline]
+    pub(crate) fn clip_bounds(&self) -> (i32, i32, i32, i32) {
+        let (x_min, y_min, x_max, y_max) = self.clip();
+        let (ox, oy) = self.offset;
+        (
+            x_min.saturating_sub(ox),
+            y_min.saturating_sub(oy),
+            x_max.saturating_sub(ox),
+            y_max.saturating_sub(oy),
+        )
+    }
+
+    /// Current clip rectangle in buffer coordinates
+    #[inline(always)]
+    fn clip(&self) -> Rect {
+        self.clips[self.clips.len() - 1]
+    }
+
+    /// Checks if the inclusive box `(x_min, y_min, x_max, y_max)` overlaps
+    /// the drawable area. Coordinates are widened so that boxes of shapes
+    /// placed near the `i32` limits do not overflow.
+    #[inline]
+    pub(crate) fn is_visible(&self, x_min: i64, y_min: i64, x_max: i64, y_max: i64) -> bool {
+        let (cx_min, cy_min, cx_max, cy_max) = self.clip_bounds();
+        x_max >= cx_min as i64
+            && x_min <= cx_max as i64
+            && y_max >= cy_min as i64
+            && y_min <= cy_max as i64
+    }
+
+    /// Checks if the point lies inside the drawable area
     #[inline(always)]
-    pub(crate) fn draw_point(&mut self, x: i32, y: i32, color: &Color, buffer: &mut [u8]) {
-        // TODO: take care of mapping from user's coordinate plane to canvas
-        // Use origin shifting
-        self.draw_point_internal(x as usize, y as usize, color, buffer)
+    fn contains(&self, x: i32, y: i32) -> bool {
+        let (x_min, y_min, x_max, y_max) = self.clip_bounds();
+        x >= x_min && x <= x_max && y >= y_min && y <= y_max
+    }
+
+    /// Whether the shapes can be handed whole to the backend of the canvas,
+    /// see [`Backend`] for when they are
+    pub(crate) fn has_shape_backend(&self) -> bool {
+        let backend = match &self.storage {
+            Storage::Backend(backend) => backend,
+            _ => return false,
+        };
+        let bounds = (
+            0,
+            0,
+            backend.width().min(i32::MAX as usize) as i32 - 1,
+            backend.height().min(i32::MAX as usize) as i32 - 1,
+        );
+        let solid = Stroke {
+            width: self.stroke.width,
+            ..Stroke::default()
+        };
+        self.offset == (0, 0)
+            && self.clip() == bounds
+            && self.composite_mode == CompositeMode::default()
+            && self.blend_mode == BlendMode::default()
+            && self.stroke == solid
     }
 
+    /// Hands a shape to the backend of the canvas along with the line width,
+    /// returning whether `draw` drew it. Shapes which the backend cannot be
+    /// handed are left to the canvas.
+    pub(crate) fn draw_with_backend<F>(&mut self, draw: F) -> bool
+    where
+        F: FnOnce(&mut dyn Backend, f32) -> bool,
+    {
+        if !self.has_shape_backend() {
+            return false;
+        }
+        let width = self.stroke.width;
+        match &mut self.storage {
+            Storage::Backend(backend) => draw(&mut **backend, width),
+            _ => false,
+        }
+    }
+
+    /// Maps a point from shape coordinates to the nearest pixel
+    #[inline]
+    pub(crate) fn map_point(&self, x: i32, y: i32) -> (i32, i32) {
+        if self.mapping.is_identity() {
+            return (x, y);
+        }
+        let (x, y) = self.mapping.apply(x as f64, y as f64);
+        (x.round() as i32, y.round() as i32)
+    }
+
+    /// Maps a point from shape coordinates to pixels
+    #[inline]
+    pub(crate) fn map_point_f32(&self, x: f32, y: f32) -> (f32, f32) {
+        if self.mapping.is_identity() {
+            return (x, y);
+        }
+        self.to_pixel(x, y)
+    }
+
+    /// Maps a point from shape coordinates to pixels, in double precision
+    #[inline]
+    pub(crate) fn map_point_f64(&self, x: f64, y: f64) -> (f64, f64) {
+        self.mapping.apply(x, y)
+    }
+
+    /// Whether shapes keep their horizontal and vertical edges once mapped
+    /// to pixels
+    #[inline]
+    pub(crate) fn is_axis_aligned(&self) -> bool {
+        self.mapping.transform.is_axis_aligned()
+    }
+
+    /// Maps the radii of an axis aligned ellipse to pixels, valid only if
+    /// [`Canvas::is_axis_aligned`] holds
+    #[inline]
+    pub(crate) fn map_radii(&self, width_radius: i32, height_radius: i32) -> (i32, i32) {
+        let sx = self.mapping.scale.0 as f64 * self.mapping.transform.a as f64;
+        let sy = self.mapping.scale.1 as f64 * self.mapping.transform.d as f64;
+        if sx == 1.0 && sy == 1.0 {
+            return (width_radius, height_radius);
+        }
+        (
+            (width_radius as f64 * sx.abs()).round() as i32,
+            (height_radius as f64 * sy.abs()).round() as i32,
+        )
+    }
+
+    /// Plots a point given in pixels of the canvas
     #[inline(always)]
-    fn draw_point_internal(&mut self, x: usize, y: usize, color: &Color, buffer: &mut [u8]) {
-        if x > self.w_minus_1 || y > self.h_minus_1 {
+    pub(crate) fn draw_point(&mut self, x: i32, y: i32, color: &Color) {
+        self.blend_point(x, y, color, 1.0)
+    }
+
+    /// Blends and composites the color onto the existing pixel using the
+    /// modes of the canvas, `coverage` in `[0, 1]` being the fraction of the
+    /// pixel covered by the shape.
+    #[inline]
+    pub(crate) fn blend_point(&mut self, x: i32, y: i32, color: &Color, coverage: f32) {
+        if !self.contains(x, y) || coverage <= 0.0 {
             return;
         }
-        let si = (x + y * self.width) * 4;
-        buffer[si] = color.r;
-        buffer[si + 1] = color.g;
-        buffer[si + 2] = color.b;
-        buffer[si + 3] = color.a;
+        let (x, y) = (x + self.offset.0, y + self.offset.1);
+
+        // fully covered pixels which simply get replaced
+        let opaque = color.a == 255 && self.composite_mode == CompositeMode::SourceOver;
+        let copy = self.composite_mode == CompositeMode::Source;
+        if coverage >= 1.0 && self.blend_mode == BlendMode::Normal && (opaque || copy) {
+            self.draw_point_internal(x as usize, y as usize, color);
+            return;
+        }
+
+        let dst = self.read_point_internal(x as usize, y as usize);
+        let out = composite(self.composite_mode, self.blend_mode, color, &dst, coverage);
+        self.draw_point_internal(x as usize, y as usize, &out);
+    }
+
+    /// Plots the pixels of row `y` from `x_start` up to, but excluding,
+    /// `x_end`, given in pixels of the canvas. The span is clipped and the
+    /// color composited like single points.
+    pub(crate) fn draw_span(&mut self, x_start: i32, x_end: i32, y: i32, color: &Color) {
+        let (x_min, y_min, x_max, y_max) = self.clip_bounds();
+        let (x_start, x_end) = (x_start.max(x_min), x_end.min(x_max.saturating_add(1)));
+        if y < y_min || y > y_max || x_start >= x_end {
+            return;
+        }
+        let (x, y) = (
+            (x_start + self.offset.0) as usize,
+            (y + self.offset.1) as usize,
+        );
+        let len = (x_end - x_start) as usize;
+
+        let opaque = color.a == 255 && self.composite_mode == CompositeMode::SourceOver;
+        let copy = self.composite_mode == CompositeMode::Source;
+        if self.blend_mode == BlendMode::Normal && (opaque || copy) {
+            self.fill_span_internal(x, y, len, color);
+        } else {
+            self.blend_span_internal(x, y, len, color);
+        }
+    }
+
+    #[inline(always)]
+    fn draw_point_internal(&mut self, x: usize, y: usize, color: &Color) {
+        let bpp = self.format.bytes_per_pixel();
+        let si = x * bpp + y * self.stride;
+        match &mut self.storage {
+            Storage::Owned(buffer) => self.format.write(&mut buffer[si..si + bpp], color),
+            Storage::Bytes(buffer) => self.format.write(&mut buffer[si..si + bpp], color),
+            Storage::Words(buffer) => {
+                let mut pixel = [0u8; 4];
+                self.format.write(&mut pixel, color);
+                buffer[si / 4] = u32::from_ne_bytes(pixel);
+            }
+            Storage::Backend(backend) => backend.set_pixel(x, y, color),
+        }
+    }
+
+    #[inline(always)]
+    fn read_point_internal(&self, x: usize, y: usize) -> Color {
+        let bpp = self.format.bytes_per_pixel();
+        let si = x * bpp + y * self.stride;
+        match &self.storage {
+            Storage::Owned(buffer) => self.format.read(&buffer[si..si + bpp]),
+            Storage::Bytes(buffer) => self.format.read(&buffer[si..si + bpp]),
+            Storage::Words(buffer) => self.format.read(&buffer[si / 4].to_ne_bytes()),
+            Storage::Backend(backend) => backend.get_pixel(x, y),
+        }
+    }
+
+    /// Position in the buffer of the pixel, `None` if it lies outside of
+    /// the drawable area
+    fn buffer_position(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let (x_min, y_min, x_max, y_max) = self.clips[0];
+        let x = x as i64 + self.offset.0 as i64;
+        let y = y as i64 + self.offset.1 as i64;
+        if x < x_min as i64 || x > x_max as i64 || y < y_min as i64 || y > y_max as i64 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// Replaces `len` pixels of row `y` starting at `x`, encoding the color
+    /// once for the whole span
+    fn fill_span_internal(&mut self, x: usize, y: usize, len: usize, color: &Color) {
+        let bpp = self.format.bytes_per_pixel();
+        let si = x * bpp + y * self.stride;
+        let mut pixel = [0u8; 4];
+        self.format.write(&mut pixel[..bpp], color);
+        // the span is filled by doubling the already encoded part
+        let fill = |buffer: &mut [u8]| {
+            let span = &mut buffer[si..si + len * bpp];
+            span[..bpp].copy_from_slice(&pixel[..bpp]);
+            let mut filled = bpp;
+            while filled < span.len() {
+                let count = filled.min(span.len() - filled);
+                span.copy_within(..count, filled);
+                filled += count;
+            }
+        };
+        match &mut self.storage {
+            Storage::Owned(buffer) => fill(buffer),
+            Storage::Bytes(buffer) => fill(buffer),
+            Storage::Words(buffer) => {
+                buffer[si / 4..si / 4 + len].fill(u32::from_ne_bytes(pixel));
+            }
+            Storage::Backend(backend) => backend.fill_span(x, y, len, color),
+        }
+    }
+
+    /// Composites the color onto `len` pixels of row `y` starting at `x`
+    fn blend_span_internal(&mut self, x: usize, y: usize, len: usize, color: &Color) {
+        let (mode, blend) = (self.composite_mode, self.blend_mode);
+        if let Storage::Backend(backend) = &mut self.storage {
+            backend.blend_span(x, y, len, &|dst| composite(mode, blend, color, dst, 1.0));
+            return;
+        }
+        for x in x..x + len {
+            let dst = self.read_point_internal(x, y);
+            let out = composite(mode, blend, color, &dst, 1.0);
+            self.draw_point_internal(x, y, &out);
+        }
+    }
+}
+
+/// Raw access to the pixels of the drawable area, in pixels of the canvas
+/// and regardless of its mapping and compositing. Pixels outside of the
+/// area are ignored and read as transparent black.
+impl Backend for Canvas<'_> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> Color {
+        match self.buffer_position(x, y) {
+            Some((x, y)) => self.read_point_internal(x, y),
+            None => Color::new(0, 0, 0, 0),
+        }
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: &Color) {
+        if let Some((x, y)) = self.buffer_position(x, y) {
+            self.draw_point_internal(x, y, color);
+        }
+    }
+}
+
+/// Rows of the buffer of a canvas drawn by one thread
+#[cfg(feature = "parallel")]
+enum Band<'b> {
+    Bytes(&'b mut [u8]),
+    Words(&'b mut [u32]),
+}
+
+/// State of a canvas which is shared with the threads drawing its bands
+#[cfg(feature = "parallel")]
+struct Settings {
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    stride: usize,
+    offset: (i32, i32),
+    clip: Rect,
+    mapping: Mapping,
+    antialias: bool,
+    composite_mode: CompositeMode,
+    blend_mode: BlendMode,
+    stroke: Stroke,
+}
+
+#[cfg(feature = "parallel")]
+impl Settings {
+    /// Canvas drawing the rows of the band, which starts at row `y_start`
+    /// of the buffer. It keeps the local coordinates of the whole canvas,
+    /// its clip being restricted to the band.
+    fn band<'b>(&self, band: Band<'b>, y_start: usize, rows: usize) -> Canvas<'b> {
+        let y_start = y_start as i32;
+        let y_end = (y_start as i64 + rows as i64 - 1).min(i32::MAX as i64) as i32;
+        let clip = intersect(self.clip, (self.clip.0, y_start, self.clip.2, y_end));
+        let storage = match band {
+            Band::Bytes(buffer) => Storage::Bytes(buffer),
+            Band::Words(buffer) => Storage::Words(buffer),
+        };
+        Canvas {
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            stride: self.stride,
+            storage,
+            offset: (self.offset.0, self.offset.1 - y_start),
+            mapping: self.mapping,
+            clips: vec![(clip.0, clip.1 - y_start, clip.2, clip.3 - y_start)],
+            antialias: self.antialias,
+            composite_mode: self.composite_mode,
+            blend_mode: self.blend_mode,
+            stroke: self.stroke.clone(),
+        }
+    }
+}
+
+/// Intersection of two inclusive rectangles, empty rectangles having
+/// `x_max < x_min` or `y_max < y_min`
+fn intersect(a: Rect, b: Rect) -> Rect {
+    (a.0.max(b.0), a.1.max(b.1), a.2.min(b.2), a.3.min(b.3))
+}
+
+/// Number of bytes taken by `width * height` pixels, `None` on overflow
+fn buffer_size(width: usize, height: usize, bytes_per_pixel: usize) -> Option<usize> {
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(bytes_per_pixel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::shape::FillRule;
+    use test::Bencher;
+
+    #[test]
+    fn test_owned_buffer() {
+        let mut canvas = Canvas::with_format(3, 2, PixelFormat::Rgb888).unwrap();
+        assert_eq!(canvas.bytes(), Some(&[0u8; 18][..]));
+
+        canvas.clear(&color::RED);
+        assert_eq!(canvas.get_color(2, 1), Some(color::RED));
+        assert_eq!(&canvas.bytes().unwrap()[15..], &[255, 0, 0]);
+        assert_eq!(canvas.words(), None);
+    }
+
+    #[test]
+    fn test_buffer_size_mismatch() {
+        let mut buffer = vec![0u8; 4 * 10 * 10];
+        assert!(Canvas::from_bytes(10, 10, PixelFormat::Rgba8888, &mut buffer).is_ok());
+        assert!(Canvas::from_bytes(10, 11, PixelFormat::Rgba8888, &mut buffer).is_err());
+        assert!(Canvas::from_bytes(10, 10, PixelFormat::Rgb888, &mut buffer).is_err());
+        assert!(Canvas::from_bytes(usize::MAX, 2, PixelFormat::Gray8, &mut buffer).is_err());
+
+        let mut words = vec![0u32; 10 * 10];
+        assert!(Canvas::from_words(10, 10, PixelFormat::Xrgb8888, &mut words).is_ok());
+        assert!(Canvas::from_words(10, 9, PixelFormat::Xrgb8888, &mut words).is_err());
+        assert!(Canvas::from_words(10, 10, PixelFormat::Rgb565, &mut words).is_err());
+    }
+
+    #[test]
+    fn test_words_buffer() {
+        let mut words = vec![0u32; 4 * 4];
+        {
+            let mut canvas = Canvas::from_words(4, 4, PixelFormat::Argb8888, &mut words).unwrap();
+            shape::draw_line2d(0, 0, 3, 0, &mut canvas, &Color::new(1, 2, 3, 4));
+            assert_eq!(canvas.get_color(3, 0), Some(Color::new(1, 2, 3, 4)));
+            assert_eq!(canvas.bytes(), None);
+        }
+        assert_eq!(
+            &words[..5],
+            &[0x0401_0203, 0x0401_0203, 0x0401_0203, 0x0401_0203, 0]
+        );
+    }
+
+    #[test]
+    fn test_stride_padding() {
+        // rows of 3 RGBA pixels padded to 16 bytes
+        let mut buffer = [7u8; 16 * 4];
+        {
+            let mut canvas =
+                Canvas::from_bytes_with_stride(3, 4, PixelFormat::Rgba8888, 16, &mut buffer)
+                    .unwrap();
+            canvas.clear(&color::BLACK);
+            shape::draw_line2d(0, 0, 2, 3, &mut canvas, &color::WHITE);
+            assert_eq!(canvas.get_color(2, 3), Some(color::WHITE));
+        }
+
+        for row in buffer.chunks(16) {
+            assert!(row[..12].chunks(4).all(|p| p[3] == 255));
+            assert_eq!(&row[12..], &[7, 7, 7, 7]);
+        }
+        assert_eq!(&buffer[3 * 16 + 8..3 * 16 + 12], &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_translucent_overlay() {
+        let mut canvas = Canvas::new(10, 10).unwrap();
+
+        shape::draw_rect2d_filled(0, 0, 6, 6, &mut canvas, &color::BLUE);
+        let half_red = Color::new(255, 0, 0, 128);
+        shape::draw_rect2d_filled(3, 3, 6, 6, &mut canvas, &half_red);
+
+        assert_eq!(canvas.get_color(1, 1), Some(Color::from([0, 0, 255, 255])));
+        assert_eq!(
+            canvas.get_color(4, 4),
+            Some(Color::from([128, 0, 127, 255]))
+        );
+        assert_eq!(canvas.get_color(7, 7), Some(Color::from([255, 0, 0, 128])));
+    }
+
+    #[test]
+    fn test_composite_mode() {
+        let mut canvas = Canvas::new(10, 10).unwrap();
+
+        shape::draw_rect2d_filled(0, 0, 6, 6, &mut canvas, &color::BLUE);
+        canvas.set_composite_mode(CompositeMode::DestinationOut);
+        shape::draw_rect2d_filled(3, 3, 6, 6, &mut canvas, &color::RED);
+
+        // red punches a hole in the blue rectangle
+        assert_eq!(canvas.get_color(1, 1), Some(Color::from([0, 0, 255, 255])));
+        assert_eq!(canvas.get_color(4, 4), Some(Color::from([0, 0, 0, 0])));
+        assert_eq!(canvas.get_color(7, 7), Some(Color::from([0, 0, 0, 0])));
+    }
+
+    #[test]
+    fn test_spans_in_formats() {
+        // spans and clearing match plotting the pixels one by one
+        let formats = [
+            PixelFormat::Rgba8888,
+            PixelFormat::Argb8888,
+            PixelFormat::Rgb888,
+            PixelFormat::Gray8,
+            PixelFormat::Rgb565,
+        ];
+        for &format in formats.iter() {
+            let mut canvas = Canvas::with_format(12, 9, format).unwrap();
+            canvas.push_clip(1, 2, 10, 6);
+            canvas.clear(&color::BLUE);
+            shape::draw_rect2d_filled(-3, 4, 9, 2, &mut canvas, &color::RED);
+            shape::draw_ellipse2d_filled(8, 4, 3, 2, &mut canvas, &color::GREEN);
+
+            let mut expected = Canvas::with_format(12, 9, format).unwrap();
+            for y in 2..8 {
+                for x in 1..11 {
+                    expected.draw_point(x, y, &color::BLUE);
+                }
+            }
+            for y in 4..6 {
+                for x in 1..6 {
+                    expected.draw_point(x, y, &color::RED);
+                }
+            }
+            for (y, half_width) in [(2, 1), (3, 3), (4, 3), (5, 3), (6, 1)].iter() {
+                for x in 8 - half_width..=(8 + half_width).min(10) {
+                    expected.draw_point(x, *y, &color::GREEN);
+                }
+            }
+            assert_eq!(canvas.bytes(), expected.bytes(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_stride_sub_region() {
+        // draw into the 4x4 region at (2, 1) of an 8x8 gray surface
+        let mut surface = [0u8; 8 * 8];
+        {
+            let start = 8 + 2;
+            let region = &mut surface[start..start + 3 * 8 + 4];
+            let mut canvas =
+                Canvas::from_bytes_with_stride(4, 4, PixelFormat::Gray8, 8, region).unwrap();
+            shape::draw_rect2d_filled(-10, -10, 20, 20, &mut canvas, &color::WHITE);
+        }
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (1..5).contains(&y);
+                assert_eq!(surface[x + y * 8], if inside { 255 } else { 0 });
+            }
+        }
+    }
+
+    #[test]
+    fn test_stride_too_small() {
+        let mut buffer = [0u8; 64];
+        let format = PixelFormat::Rgba8888;
+        assert!(Canvas::from_bytes_with_stride(4, 4, format, 15, &mut buffer).is_err());
+        assert!(Canvas::from_bytes_with_stride(4, 2, format, 48, &mut buffer).is_ok());
+        assert!(Canvas::from_bytes_with_stride(4, 2, format, 49, &mut buffer).is_err());
+        assert!(Canvas::from_bytes_with_stride(4, 4, PixelFormat::Rgb565, 8, &mut buffer).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_stroke_settings() {
+        let mut canvas = Canvas::new(10, 10).unwrap();
+        canvas.set_line_width(3.0);
+        canvas.set_line_width(f32::NAN);
+        canvas.set_line_width(f32::INFINITY);
+        assert_eq!(canvas.line_width(), 3.0);
+        canvas.set_line_width(-2.0);
+        assert_eq!(canvas.line_width(), 0.0);
+
+        canvas.set_miter_limit(4.0);
+        canvas.set_miter_limit(f32::NAN);
+        canvas.set_miter_limit(f32::INFINITY);
+        canvas.set_miter_limit(0.0);
+        canvas.set_miter_limit(-1.0);
+        assert_eq!(canvas.miter_limit(), 4.0);
+
+        canvas.set_line_dash(&[2.0, 1.0]);
+        canvas.set_line_dash(&[2.0, -1.0]);
+        canvas.set_line_dash(&[0.0, 0.0]);
+        assert_eq!(canvas.line_dash(), &[2.0, 1.0]);
+        canvas.set_line_dash_offset(1.0);
+        canvas.set_line_dash_offset(f32::NAN);
+        assert_eq!(canvas.line_dash_offset(), 1.0);
+    }
+
+    #[test]
+    fn test_sub_canvas() {
+        let mut canvas = Canvas::new(10, 10).unwrap();
+        {
+            let mut panel = canvas.sub_canvas(2, 3, 4, 4);
+            assert_eq!((panel.width(), panel.height()), (4, 4));
+
+            // local origin is at (2, 3), nothing spills out of the panel
+            shape::draw_rect2d_filled(-5, -5, 100, 100, &mut panel, &color::RED);
+            shape::draw_line2d(0, 0, 0, 0, &mut panel, &color::WHITE);
+            assert_eq!(panel.get_color(0, 0), Some(color::WHITE));
+            assert_eq!(panel.get_color(4, 0), None);
+        }
+
+        for y in 0..10 {
+            for x in 0..10 {
+                let expected = match (x, y) {
+                    (2, 3) => color::WHITE,
+                    (2..=5, 3..=6) => color::RED,
+                    _ => Color::new(0, 0, 0, 0),
+                };
+                assert_eq!(canvas.get_color(x, y), Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_nested_sub_canvas() {
+        let mut canvas = Canvas::new(10, 10).unwrap();
+        canvas.push_clip(0, 0, 6, 10);
+        {
+            // partially outside of the clip of its parent
+            let mut panel = canvas.sub_canvas(4, 4, 4, 4);
+            let mut inner = panel.sub_canvas(1, -1, 10, 2);
+            inner.clear(&color::RED);
+        }
+        canvas.pop_clip();
+
+        let red: Vec<(i32, i32)> = (0..100)
+            .map(|i| (i % 10, i / 10))
+            .filter(|&(x, y)| canvas.get_color(x, y) == Some(color::RED))
+            .collect();
+        assert_eq!(red, vec![(5, 4)]);
+    }
+
+    #[test]
+    fn test_clip_stack() {
+        let mut canvas = Canvas::new(10, 10).unwrap();
+        let empty = Some(Color::new(0, 0, 0, 0));
+
+        canvas.push_clip(2, 2, 6, 6);
+        canvas.push_clip(5, 0, 10, 4);
+        shape::draw_ellipse2d_filled(5, 5, 10, 10, &mut canvas, &color::RED);
+        assert_eq!(canvas.get_color(5, 2), Some(color::RED));
+        assert_eq!(canvas.get_color(7, 3), Some(color::RED));
+        assert_eq!(canvas.get_color(4, 2), empty);
+        assert_eq!(canvas.get_color(8, 3), empty);
+        assert_eq!(canvas.get_color(5, 4), empty);
+
+        assert!(canvas.pop_clip());
+        shape::draw_rect2d_filled(0, 6, 10, 4, &mut canvas, &color::GREEN);
+        assert_eq!(canvas.get_color(2, 7), Some(color::GREEN));
+        assert_eq!(canvas.get_color(1, 7), empty);
+        assert_eq!(canvas.get_color(2, 8), empty);
+
+        assert!(canvas.pop_clip());
+        assert!(!canvas.pop_clip());
+        shape::draw_line2d(0, 9, 9, 9, &mut canvas, &color::BLUE);
+        assert_eq!(canvas.get_color(0, 9), Some(color::BLUE));
+    }
+
+    #[test]
+    fn test_y_up_origin() {
+        let mut canvas = Canvas::new(10, 10).unwrap();
+        let empty = Some(Color::new(0, 0, 0, 0));
+        canvas.set_origin(0.0, 9.0);
+        canvas.set_y_up(true);
+        assert_eq!(canvas.to_pixel(2.0, 3.0), (2.0, 6.0));
+
+        shape::draw_rect2d_filled(0, 0, 2, 2, &mut canvas, &color::RED);
+        shape::draw_line2d(0, 5, 9, 5, &mut canvas, &color::GREEN);
+
+        // shape coordinates read back the same
+        assert_eq!(canvas.get_color(0, 0), Some(color::RED));
+        assert_eq!(canvas.get_color(1, 1), Some(color::RED));
+        assert_eq!(canvas.get_color(0, 2), empty);
+        assert_eq!(canvas.get_color(4, 5), Some(color::GREEN));
+
+        // while in the buffer they are flipped
+        canvas.set_y_up(false);
+        canvas.set_origin(0.0, 0.0);
+        assert_eq!(canvas.get_color(0, 9), Some(color::RED));
+        assert_eq!(canvas.get_color(1, 8), Some(color::RED));
+        assert_eq!(canvas.get_color(0, 7), empty);
+        assert_eq!(canvas.get_color(4, 4), Some(color::GREEN));
+    }
+
+    #[test]
+    fn test_viewport() {
+        let mut canvas = Canvas::new(20, 20).unwrap();
+        let empty = Some(Color::new(0, 0, 0, 0));
+        canvas.set_viewport((2.0, 2.0), (1.0, 1.0));
+
+        shape::draw_rect2d_filled(1, 1, 2, 3, &mut canvas, &color::RED);
+        shape::draw_ellipse2d_filled(6, 6, 2, 1, &mut canvas, &color::BLUE);
+
+        let mut pixels = Canvas::new(20, 20).unwrap();
+        shape::draw_rect2d_filled(3, 3, 4, 6, &mut pixels, &color::RED);
+        shape::draw_ellipse2d_filled(13, 13, 4, 2, &mut pixels, &color::BLUE);
+        assert_eq!(canvas.bytes(), pixels.bytes());
+
+        canvas.set_viewport((1.0, 1.0), (0.0, 0.0));
+        assert_eq!(canvas.get_color(3, 3), Some(color::RED));
+        assert_eq!(canvas.get_color(6, 8), Some(color::RED));
+        assert_eq!(canvas.get_color(7, 3), empty);
+        assert_eq!(canvas.get_color(3, 9), empty);
+    }
+
+    #[test]
+    fn test_draw_transformed() {
+        let mut canvas = Canvas::new(30, 30).unwrap();
+        let empty = Some(Color::new(0, 0, 0, 0));
+        let quarter = core::f32::consts::FRAC_PI_2;
+
+        // rectangle turned upright around (10, 10)
+        let rect = shape::Rectangle2D::new(0, 0, 10, 4, true);
+        let transform = Transform::translation(10.0, 10.0).rotate(quarter);
+        canvas.draw_transformed(&rect, &transform, &color::RED);
+        assert!(canvas.transform().is_identity());
+        assert_eq!(canvas.get_color(8, 15), Some(color::RED));
+        assert_eq!(canvas.get_color(8, 21), empty);
+        assert_eq!(canvas.get_color(12, 15), empty);
+
+        // ellipse along the diagonal
+        let ellipse = shape::Ellipse2D::new(0, 0, 8, 2, true);
+        let transform = Transform::translation(20.0, 20.0).rotate(quarter / 2.0);
+        canvas.draw_transformed(&ellipse, &transform, &color::BLUE);
+        assert_eq!(canvas.get_color(25, 25), Some(color::BLUE));
+        assert_eq!(canvas.get_color(15, 15), Some(color::BLUE));
+        assert_eq!(canvas.get_color(25, 15), empty);
+        assert_eq!(canvas.get_color(26, 20), empty);
+    }
+
+    #[test]
+    fn test_scaling_transform_keeps_shapes() {
+        let mut canvas = Canvas::new(30, 30).unwrap();
+        canvas.set_transform(Transform::scaling(2.0, 2.0).translate(1.0, 1.0));
+        shape::draw_ellipse2d(5, 5, 3, 2, &mut canvas, &color::WHITE);
+        shape::draw_rect2d_filled(6, 1, 3, 2, &mut canvas, &color::RED);
+
+        let mut pixels = Canvas::new(30, 30).unwrap();
+        shape::draw_ellipse2d(12, 12, 6, 4, &mut pixels, &color::WHITE);
+        shape::draw_rect2d_filled(14, 4, 6, 4, &mut pixels, &color::RED);
+        assert_eq!(canvas.bytes(), pixels.bytes());
+    }
+
+    /// Backend keeping its pixels in a list, counting the spans it receives
+    struct Pixels {
+        width: usize,
+        pixels: Vec<Color>,
+        spans: usize,
+    }
+
+    impl Backend for Pixels {
+        fn width(&self) -> usize {
+            self.width
+        }
+
+        fn height(&self) -> usize {
+            self.pixels.len() / self.width
+        }
+
+        fn get_pixel(&self, x: usize, y: usize) -> Color {
+            self.pixels[x + y * self.width]
+        }
+
+        fn set_pixel(&mut self, x: usize, y: usize, color: &Color) {
+            self.pixels[x + y * self.width] = *color;
+        }
+
+        fn fill_span(&mut self, x: usize, y: usize, len: usize, color: &Color) {
+            self.spans += 1;
+            self.pixels[x + y * self.width..x + len + y * self.width].fill(*color);
+        }
+    }
+
+    fn draw_scene(canvas: &mut Canvas) {
+        let translucent = Color::new(0, 0, 255, 100);
+        let (xi, yi) = ([2, 28, 15], [28, 26, 4]);
+        canvas.push_clip(1, 1, 28, 28);
+        shape::draw_polygon2d_filled(&xi, &yi, FillRule::NonZero, canvas, &color::RED);
+        shape::draw_ellipse2d_filled(10, 10, 8, 6, canvas, &translucent);
+        canvas.set_antialias(true);
+        shape::draw_line2d_aa(0, 29, 29, 0, canvas, &color::GREEN);
+    }
+
+    #[test]
+    fn test_backend() {
+        let mut expected = Canvas::new(30, 30).unwrap();
+        draw_scene(&mut expected);
+
+        let mut backend = Pixels {
+            width: 30,
+            pixels: vec![Color::new(0, 0, 0, 0); 900],
+            spans: 0,
+        };
+        let mut canvas = Canvas::from_backend(&mut backend);
+        assert_eq!((canvas.width(), canvas.height()), (30, 30));
+        assert_eq!(canvas.bytes(), None);
+        draw_scene(&mut canvas);
+        assert_eq!(canvas.get_color(15, 20), expected.get_color(15, 20));
+
+        // opaque rows of the polygon arrive as spans
+        assert!(backend.spans > 0);
+        let bytes: Vec<u8> = backend
+            .pixels
+            .iter()
+            .flat_map(|&color| <[u8; 4]>::from(color))
+            .collect();
+        assert_eq!(Some(&bytes[..]), expected.bytes());
+    }
+
+    #[test]
+    fn test_canvas_as_backend() {
+        let mut expected = Canvas::with_format(30, 30, PixelFormat::Rgb565).unwrap();
+        draw_scene(&mut expected);
+
+        let mut target = Canvas::with_format(30, 30, PixelFormat::Rgb565).unwrap();
+        draw_scene(&mut Canvas::from_backend(&mut target));
+        assert_eq!(target.bytes(), expected.bytes());
+    }
+
+    /// Backend drawing lines and ellipses itself by recording them, leaving
+    /// paths to the canvas
+    struct Recorder {
+        pixels: Pixels,
+        shapes: Vec<String>,
+    }
+
+    impl Backend for Recorder {
+        fn width(&self) -> usize {
+            self.pixels.width()
+        }
+
+        fn height(&self) -> usize {
+            self.pixels.height()
+        }
+
+        fn get_pixel(&self, x: usize, y: usize) -> Color {
+            self.pixels.get_pixel(x, y)
+        }
+
+        fn set_pixel(&mut self, x: usize, y: usize, color: &Color) {
+            self.pixels.set_pixel(x, y, color);
+        }
+
+        fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, _: &Color) -> bool {
+            self.shapes
+                .push(format!("line {:?} {:?} {}", from, to, width));
+            true
+        }
+
+        fn ellipse(
+            &mut self,
+            center: (f32, f32),
+            radii: (f32, f32),
+            filled: bool,
+            width: f32,
+            _: &Color,
+        ) -> bool {
+            self.shapes.push(format!(
+                "ellipse {:?} {:?} {} {}",
+                center, radii, filled, width
+            ));
+            true
+        }
+    }
+
+    #[test]
+    fn test_backend_shapes() {
+        let mut backend = Recorder {
+            pixels: Pixels {
+                width: 30,
+                pixels: vec![Color::new(0, 0, 0, 0); 900],
+                spans: 0,
+            },
+            shapes: Vec::new(),
+        };
+        let mut path = shape::Path2D::new(false);
+        path.move_to(2.0, 2.0).line_to(20.0, 2.0);
+        let blank = |backend: &Recorder| backend.pixels.pixels.iter().all(|c| c.a == 0);
+
+        let mut canvas = Canvas::from_backend(&mut backend);
+        canvas.set_origin(5.0, 0.0);
+        canvas.set_line_width(3.0);
+        canvas.draw(&shape::Line2D::new(0, 1, 10, 1), &color::WHITE);
+        canvas.draw(&shape::Ellipse2D::new(10, 10, 4, 3, true), &color::WHITE);
+        assert_eq!(
+            backend.shapes,
+            [
+                "line (5.0, 1.0) (15.0, 1.0) 3",
+                "ellipse (15.0, 10.0) (4.0, 3.0) true 3"
+            ]
+        );
+        assert!(blank(&backend));
+
+        // the canvas rasterizes the paths left by the backend
+        let mut canvas = Canvas::from_backend(&mut backend);
+        canvas.draw(&path, &color::WHITE);
+        assert_eq!(backend.shapes.len(), 2);
+        assert_eq!(backend.pixels.get_pixel(10, 2), color::WHITE);
+
+        // and the shapes it could not hand over whole
+        backend.pixels.pixels.fill(Color::new(0, 0, 0, 0));
+        let mut canvas = Canvas::from_backend(&mut backend);
+        canvas.set_line_dash(&[2.0, 2.0]);
+        canvas.draw(&shape::Line2D::new(0, 1, 10, 1), &color::WHITE);
+        canvas.set_line_dash(&[]);
+        canvas.push_clip(0, 0, 20, 20);
+        canvas.draw(&shape::Ellipse2D::new(10, 10, 4, 3, true), &color::WHITE);
+        canvas.pop_clip();
+        canvas.set_composite_mode(CompositeMode::Xor);
+        canvas.draw(&shape::Line2D::new(0, 20, 10, 20), &color::WHITE);
+        canvas.set_composite_mode(CompositeMode::default());
+        canvas
+            .sub_canvas(2, 0, 28, 30)
+            .draw(&shape::Line2D::new(0, 25, 10, 25), &color::WHITE);
+        assert_eq!(backend.shapes.len(), 2);
+        assert_eq!(backend.pixels.get_pixel(0, 1), color::WHITE);
+        assert_eq!(backend.pixels.get_pixel(10, 10), color::WHITE);
+        assert_eq!(backend.pixels.get_pixel(5, 20), color::WHITE);
+        assert_eq!(backend.pixels.get_pixel(7, 25), color::WHITE);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_serial_long_curves() {
+        // curves long enough to be split, crossing the edges of the bands
+        let cubic = shape::CubicBezier2D::new((10, 390), (390, 10), (600, 300), (-200, 100));
+        let quadratic = shape::QuadraticBezier2D::new((-50, 20), (450, 380), (300, -100));
+        let mut path = shape::Path2D::new(false);
+        path.move_to(20.0, 200.0)
+            .arc_to(380.0, 200.0, 380.0, 380.0, 170.0)
+            .cubic_to(500.0, 0.0, -100.0, 0.0, 30.0, 390.0);
+        let shapes: [(&(dyn shape::Shape + Sync), Color); 3] = [
+            (&cubic, color::WHITE),
+            (&quadratic, Color::new(255, 0, 0, 128)),
+            (&path, Color::new(0, 255, 0, 160)),
+        ];
+
+        for &(width, antialias) in [(1.0, false), (1.0, true), (6.0, false)].iter() {
+            let setup = |canvas: &mut Canvas| {
+                canvas.set_line_width(width);
+                canvas.set_antialias(antialias);
+                canvas.push_clip(15, 20, 370, 350);
+            };
+            let mut expected = Canvas::new(400, 400).unwrap();
+            setup(&mut expected);
+            for (shape, color) in shapes.iter() {
+                expected.draw(*shape, color);
+            }
+
+            for &bands in [2, 3, 5, 8].iter() {
+                let mut canvas = Canvas::new(400, 400).unwrap();
+                setup(&mut canvas);
+                canvas.draw_in_bands(&shapes, bands);
+                assert_eq!(canvas.bytes(), expected.bytes(), "{} bands", bands);
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_serial() {
+        let translucent = Color::new(0, 0, 255, 100);
+        let ellipse = shape::Ellipse2D::new(30, 25, 22, 14, true);
+        let rect = shape::Rectangle2D::new(4, 6, 40, 30, false);
+        let (xi, yi) = ([2, 58, 30, 50, 8], [2, 10, 45, 3, 40]);
+        let polygon = shape::Polygon2D::new(&xi, &yi, true);
+        let bezier = shape::CubicBezier2D::new((0, 45), (60, 0), (80, 60), (-20, 0));
+        let mut path = shape::Path2D::new(false);
+        path.move_to(5.0, 40.0)
+            .arc_to(50.0, 40.0, 50.0, 5.0, 12.0)
+            .line_to(20.0, 10.0);
+        let shapes: [(&(dyn shape::Shape + Sync), Color); 5] = [
+            (&polygon, color::RED),
+            (&ellipse, translucent),
+            (&rect, translucent),
+            (&bezier, color::GREEN),
+            (&path, Color::new(255, 255, 0, 160)),
+        ];
+
+        let setup = |canvas: &mut Canvas, antialias: bool| {
+            canvas.set_antialias(antialias);
+            canvas.set_transform(Transform::rotation(0.2).scale(1.2, 1.1));
+            canvas.set_origin(4.0, 6.0);
+            canvas.push_clip(2, 3, 54, 40);
+        };
+        let thick = |canvas: &mut Canvas| {
+            canvas.set_line_width(3.5);
+            canvas.set_line_dash(&[6.0, 2.0]);
+            canvas.set_line_join(LineJoin::Round);
+        };
+        let serial = |canvas: &mut Canvas| {
+            for (shape, color) in shapes.iter() {
+                canvas.draw(*shape, color);
+            }
+        };
+
+        for &antialias in [false, true].iter() {
+            let mut expected = Canvas::new(60, 50).unwrap();
+            setup(&mut expected, antialias);
+            serial(&mut expected);
+            thick(&mut expected);
+            serial(&mut expected);
+
+            for bands in 1..8 {
+                let mut canvas = Canvas::new(60, 50).unwrap();
+                setup(&mut canvas, antialias);
+                canvas.draw_in_bands(&shapes, bands);
+                thick(&mut canvas);
+                canvas.draw_in_bands(&shapes, bands);
+                assert_eq!(canvas.bytes(), expected.bytes(), "{} bands", bands);
+            }
+        }
+
+        // packed pixels in a view of a larger buffer
+        let mut expected = vec![0u32; 80 * 60];
+        let mut buffer = vec![0u32; 80 * 60];
+        {
+            let mut parent =
+                Canvas::from_words(80, 60, PixelFormat::Argb8888, &mut expected).unwrap();
+            let mut view = parent.sub_canvas(10, 5, 60, 50);
+            setup(&mut view, true);
+            serial(&mut view);
+        }
+        {
+            let mut parent =
+                Canvas::from_words(80, 60, PixelFormat::Argb8888, &mut buffer).unwrap();
+            let mut view = parent.sub_canvas(10, 5, 60, 50);
+            setup(&mut view, true);
+            view.draw_parallel(&shapes);
+        }
+        assert_eq!(buffer, expected);
+    }
+
+    #[bench]
+    fn bench_clear(b: &mut Bencher) {
+        let mut canvas = Canvas::new(400, 400).unwrap();
+
+        b.iter(|| canvas.clear(&color::WHITE));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[bench]
+    fn bench_draw_parallel(b: &mut Bencher) {
+        let mut canvas = Canvas::new(2000, 2000).unwrap();
+        let ellipse = shape::Ellipse2D::new(1000, 1000, 600, 450, true);
+        let (xi, yi) = ([0, 1999, 1000], [0, 300, 1999]);
+        let polygon = shape::Polygon2D::new(&xi, &yi, true);
+        let shapes: [(&(dyn shape::Shape + Sync), Color); 2] = [
+            (&polygon, Color::new(255, 0, 0, 128)),
+            (&ellipse, Color::new(0, 0, 255, 128)),
+        ];
+
+        b.iter(|| canvas.draw_parallel(&shapes));
     }
 }
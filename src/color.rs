@@ -25,6 +25,20 @@ impl Color {
     }
 }
 
+impl From<[u8; 4]> for Color {
+    /// creates a color from its `[r, g, b, a]` components
+    fn from(rgba: [u8; 4]) -> Color {
+        Color::new(rgba[0], rgba[1], rgba[2], rgba[3])
+    }
+}
+
+impl From<Color> for [u8; 4] {
+    /// returns the `[r, g, b, a]` components of the color
+    fn from(color: Color) -> [u8; 4] {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
 #[macro_export]
 macro_rules! color {
     { $A:expr, $B:expr, $C:expr } => {
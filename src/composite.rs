@@ -0,0 +1,344 @@
+//! The `composite` module defines how drawn colors are combined with the
+//! pixels already present in the buffer.
+
+use crate::Color;
+
+/// [Porter-Duff](https://www.w3.org/TR/compositing-1/#porterduffcompositingoperators)
+/// compositing operators.
+///
+/// Compositing only applies to the pixels touched by a shape, pixels outside
+/// of it are left as they are.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum CompositeMode {
+    /// Neither source nor destination is kept
+    Clear,
+    /// Source replaces destination, also known as copy
+    Source,
+    /// Destination is kept as it is
+    Destination,
+    /// Source is placed over the destination
+    #[default]
+    SourceOver,
+    /// Destination is placed over the source
+    DestinationOver,
+    /// Part of source inside the destination replaces the destination
+    SourceIn,
+    /// Part of destination inside the source replaces the destination
+    DestinationIn,
+    /// Part of source outside the destination replaces the destination
+    SourceOut,
+    /// Part of destination outside the source replaces the destination
+    DestinationOut,
+    /// Part of source inside the destination is placed over the destination
+    SourceAtop,
+    /// Part of destination inside the source is placed over the source
+    DestinationAtop,
+    /// Parts of source and destination outside of each other are kept
+    Xor,
+}
+
+impl CompositeMode {
+    /// Porter-Duff fractions `(Fa, Fb)` of source and destination kept, for
+    /// source alpha `sa` and destination alpha `da`.
+    #[inline]
+    fn factors(self, sa: f32, da: f32) -> (f32, f32) {
+        match self {
+            CompositeMode::Clear => (0.0, 0.0),
+            CompositeMode::Source => (1.0, 0.0),
+            CompositeMode::Destination => (0.0, 1.0),
+            CompositeMode::SourceOver => (1.0, 1.0 - sa),
+            CompositeMode::DestinationOver => (1.0 - da, 1.0),
+            CompositeMode::SourceIn => (da, 0.0),
+            CompositeMode::DestinationIn => (0.0, sa),
+            CompositeMode::SourceOut => (1.0 - da, 0.0),
+            CompositeMode::DestinationOut => (0.0, 1.0 - sa),
+            CompositeMode::SourceAtop => (da, 1.0 - sa),
+            CompositeMode::DestinationAtop => (1.0 - da, sa),
+            CompositeMode::Xor => (1.0 - da, 1.0 - sa),
+        }
+    }
+}
+
+/// [Separable blend modes](https://www.w3.org/TR/compositing-1/#blending),
+/// mixing the source color with the destination color before compositing.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Source color is used as it is
+    #[default]
+    Normal,
+    /// Source and destination are multiplied, darkening the result
+    Multiply,
+    /// Complements are multiplied, lightening the result
+    Screen,
+    /// Multiplies or screens depending on the destination color
+    Overlay,
+    /// Darker of source and destination is kept
+    Darken,
+    /// Lighter of source and destination is kept
+    Lighten,
+    /// Absolute difference of source and destination
+    Difference,
+}
+
+impl BlendMode {
+    /// Blending function `B(cb, cs)` on a single channel in `[0, 1]`, with
+    /// `cb` the destination (backdrop) and `cs` the source.
+    #[inline]
+    fn blend(self, cb: f32, cs: f32) -> f32 {
+        match self {
+            BlendMode::Normal => cs,
+            BlendMode::Multiply => cb * cs,
+            BlendMode::Screen => cb + cs - cb * cs,
+            BlendMode::Overlay => {
+                // hard light with source and destination swapped
+                if cb <= 0.5 {
+                    BlendMode::Multiply.blend(cs, 2.0 * cb)
+                } else {
+                    BlendMode::Screen.blend(cs, 2.0 * cb - 1.0)
+                }
+            }
+            BlendMode::Darken => cb.min(cs),
+            BlendMode::Lighten => cb.max(cs),
+            BlendMode::Difference => (cb - cs).abs(),
+        }
+    }
+}
+
+/// Blends and then composites `src` onto `dst`.
+///
+/// The source color is first mixed with the destination by `blend`, as
+/// `(1 - da) * cs + da * B(cb, cs)`, and the result is composited using
+/// `mode`. `coverage` in `[0, 1]` is the fraction of the pixel covered by the
+/// shape, the result is interpolated between `dst` and the composited color
+/// by it. Colors are stored non-premultiplied, the math happens on
+/// premultiplied values.
+pub(crate) fn composite(
+    mode: CompositeMode,
+    blend: BlendMode,
+    src: &Color,
+    dst: &Color,
+    coverage: f32,
+) -> Color {
+    let coverage = coverage.clamp(0.0, 1.0);
+    let sa = src.a as f32 / 255.0;
+    let da = dst.a as f32 / 255.0;
+    let (fa, fb) = mode.factors(sa, da);
+
+    let out_a = sa * fa + da * fb;
+    let result_a = da + (out_a - da) * coverage;
+    if result_a <= 0.0 {
+        return Color::new(0, 0, 0, 0);
+    }
+
+    let channel = |s: u8, d: u8| {
+        let cs = s as f32 / 255.0;
+        let cb = d as f32 / 255.0;
+        let mixed = (1.0 - da) * cs + da * blend.blend(cb, cs);
+        let sc = mixed * sa;
+        let dc = cb * da;
+        let out_c = sc * fa + dc * fb;
+        let c = (dc + (out_c - dc) * coverage) / result_a;
+        (c.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+
+    Color::new(
+        channel(src.r, dst.r),
+        channel(src.g, dst.g),
+        channel(src.b, dst.b),
+        (result_a.clamp(0.0, 1.0) * 255.0).round() as u8,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+
+    const TRANSPARENT: Color = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+
+    fn composite_normal(mode: CompositeMode, src: &Color, dst: &Color, coverage: f32) -> Color {
+        composite(mode, BlendMode::Normal, src, dst, coverage)
+    }
+
+    /// Source over compositing with blending, straight from the W3C formulas
+    fn reference(blend: BlendMode, src: &Color, dst: &Color) -> Color {
+        let b = |cb: f64, cs: f64| match blend {
+            BlendMode::Normal => cs,
+            BlendMode::Multiply => cb * cs,
+            BlendMode::Screen => 1.0 - (1.0 - cb) * (1.0 - cs),
+            BlendMode::Overlay => {
+                if cb <= 0.5 {
+                    2.0 * cb * cs
+                } else {
+                    1.0 - 2.0 * (1.0 - cb) * (1.0 - cs)
+                }
+            }
+            BlendMode::Darken => cb.min(cs),
+            BlendMode::Lighten => cb.max(cs),
+            BlendMode::Difference => (cb - cs).abs(),
+        };
+        let sa = src.a as f64 / 255.0;
+        let ba = dst.a as f64 / 255.0;
+        let ra = sa + ba * (1.0 - sa);
+        let channel = |s: u8, d: u8| {
+            let cs = s as f64 / 255.0;
+            let cb = d as f64 / 255.0;
+            let cs = (1.0 - ba) * cs + ba * b(cb, cs);
+            let co = cs * sa + cb * ba * (1.0 - sa);
+            if ra == 0.0 {
+                0
+            } else {
+                (co / ra * 255.0).round() as u8
+            }
+        };
+        Color::new(
+            channel(src.r, dst.r),
+            channel(src.g, dst.g),
+            channel(src.b, dst.b),
+            (ra * 255.0).round() as u8,
+        )
+    }
+
+    #[test]
+    fn test_source_over() {
+        let half_red = Color::new(255, 0, 0, 128);
+        let c = composite_normal(CompositeMode::SourceOver, &half_red, &color::BLUE, 1.0);
+        assert_eq!(c, Color::new(128, 0, 127, 255));
+
+        let c = composite_normal(CompositeMode::SourceOver, &color::RED, &color::BLUE, 1.0);
+        assert_eq!(c, color::RED);
+
+        let c = composite_normal(CompositeMode::SourceOver, &half_red, &TRANSPARENT, 1.0);
+        assert_eq!(c, half_red);
+    }
+
+    #[test]
+    fn test_coverage() {
+        let c = composite_normal(CompositeMode::SourceOver, &color::WHITE, &color::BLACK, 0.5);
+        assert_eq!(c, Color::new(128, 128, 128, 255));
+
+        let c = composite_normal(CompositeMode::Source, &TRANSPARENT, &color::RED, 0.0);
+        assert_eq!(c, color::RED);
+    }
+
+    #[test]
+    fn test_porter_duff_operators() {
+        let half_red = Color::new(255, 0, 0, 128);
+        let blue = color::BLUE;
+
+        assert_eq!(
+            composite_normal(CompositeMode::Clear, &half_red, &blue, 1.0),
+            TRANSPARENT
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::Source, &half_red, &blue, 1.0),
+            half_red
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::Destination, &half_red, &blue, 1.0),
+            blue
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::DestinationOver, &half_red, &blue, 1.0),
+            blue
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::SourceIn, &half_red, &blue, 1.0),
+            half_red
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::SourceIn, &half_red, &TRANSPARENT, 1.0),
+            TRANSPARENT
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::DestinationIn, &half_red, &blue, 1.0),
+            Color::new(0, 0, 255, 128)
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::SourceOut, &half_red, &blue, 1.0),
+            TRANSPARENT
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::SourceOut, &half_red, &TRANSPARENT, 1.0),
+            half_red
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::DestinationOut, &half_red, &blue, 1.0),
+            Color::new(0, 0, 255, 127)
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::SourceAtop, &half_red, &blue, 1.0),
+            Color::new(128, 0, 127, 255)
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::DestinationAtop, &half_red, &blue, 1.0),
+            Color::new(0, 0, 255, 128)
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::Xor, &half_red, &blue, 1.0),
+            Color::new(0, 0, 255, 127)
+        );
+        assert_eq!(
+            composite_normal(CompositeMode::Xor, &color::RED, &blue, 1.0),
+            TRANSPARENT
+        );
+    }
+
+    #[test]
+    fn test_blend_modes_match_reference() {
+        let modes = [
+            BlendMode::Normal,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Overlay,
+            BlendMode::Darken,
+            BlendMode::Lighten,
+            BlendMode::Difference,
+        ];
+        let colors = [
+            Color::new(200, 100, 50, 255),
+            Color::new(10, 128, 250, 255),
+            Color::new(255, 64, 0, 128),
+            Color::new(90, 200, 30, 40),
+            Color::new(0, 0, 0, 0),
+        ];
+
+        for &blend in modes.iter() {
+            for src in colors.iter() {
+                for dst in colors.iter() {
+                    let c = composite(CompositeMode::SourceOver, blend, src, dst, 1.0);
+                    let r = reference(blend, src, dst);
+                    let close = |a: u8, b: u8| (a as i32 - b as i32).abs() <= 1;
+                    assert!(
+                        close(c.r, r.r) && close(c.g, r.g) && close(c.b, r.b) && close(c.a, r.a),
+                        "{:?} of {:?} over {:?}: {:?} != {:?}",
+                        blend,
+                        src,
+                        dst,
+                        c,
+                        r
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_blend_modes_opaque() {
+        let src = Color::rgb(200, 100, 50);
+        let dst = Color::rgb(100, 200, 250);
+        let blended = |blend| composite(CompositeMode::SourceOver, blend, &src, &dst, 1.0);
+
+        assert_eq!(blended(BlendMode::Multiply), Color::rgb(78, 78, 49));
+        assert_eq!(blended(BlendMode::Screen), Color::rgb(222, 222, 251));
+        assert_eq!(blended(BlendMode::Overlay), Color::rgb(157, 188, 247));
+        assert_eq!(blended(BlendMode::Darken), Color::rgb(100, 100, 50));
+        assert_eq!(blended(BlendMode::Lighten), Color::rgb(200, 200, 250));
+        assert_eq!(blended(BlendMode::Difference), Color::rgb(100, 100, 200));
+    }
+}
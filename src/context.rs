@@ -0,0 +1,407 @@
+use crate::canvas::Canvas;
+use crate::composite::{BlendMode, CompositeMode};
+use crate::shape::{LineCap, LineJoin, Shape, Stroke};
+use crate::transform::Transform;
+use crate::{color, Color};
+
+/// Drawing state saved and restored by a [`Context`]
+#[derive(Debug, Clone)]
+struct State {
+    transform: Transform,
+    fill_style: Color,
+    stroke_style: Color,
+    stroke: Stroke,
+    blend_mode: BlendMode,
+    composite_mode: CompositeMode,
+    antialias: bool,
+    /// number of clip rectangles pushed on the canvas by the context
+    clips: usize,
+}
+
+/// Stateful drawing on top of a [`Canvas`], in the manner of the HTML canvas
+/// 2D context.
+///
+/// The context keeps the current transform, clip, fill and stroke colors,
+/// stroke style, blend mode and composite mode, so shapes are drawn with
+/// [`fill`](Context::fill) and [`stroke`](Context::stroke) without passing
+/// them around. [`save`](Context::save) pushes a copy of that state and
+/// [`restore`](Context::restore) brings back the one saved last.
+///
+/// Settings are applied to the canvas as they change, and the canvas gets
+/// back the state it had before the context was created when the context is
+/// dropped.
+///
+/// ```ignore
+/// let mut ctx = Context::new(&mut canvas);
+/// ctx.set_fill_style(color::RED);
+/// ctx.save();
+/// ctx.translate(100.0, 100.0);
+/// ctx.rotate(0.5);
+/// ctx.fill(&Rectangle2D::new(0, 0, 50, 20, true));
+/// ctx.restore();
+/// ```
+pub struct Context<'c, 'a> {
+    canvas: &'c mut Canvas<'a>,
+    state: State,
+    saved: Vec<State>,
+    /// state of the canvas when the context was created
+    initial: State,
+}
+
+impl<'c, 'a> Context<'c, 'a> {
+    /// Creates a context drawing into `canvas`, starting from its current
+    /// settings. Fill and stroke colors start as opaque black.
+    pub fn new(canvas: &'c mut Canvas<'a>) -> Self {
+        let state = State {
+            transform: canvas.transform(),
+            fill_style: color::BLACK,
+            stroke_style: color::BLACK,
+            stroke: canvas.stroke().clone(),
+            blend_mode: canvas.blend_mode(),
+            composite_mode: canvas.composite_mode(),
+            antialias: canvas.is_antialiased(),
+            clips: 0,
+        };
+
+        Context {
+            canvas,
+            initial: state.clone(),
+            state,
+            saved: Vec::new(),
+        }
+    }
+
+    /// Canvas the context draws into, to read back its pixels and settings.
+    ///
+    /// Changes go through the context, which keeps track of the clips it
+    /// pushes to pop them again on [`restore`](Context::restore).
+    pub fn canvas(&self) -> &Canvas<'a> {
+        self.canvas
+    }
+
+    /// Pushes a copy of the current state on the stack
+    pub fn save(&mut self) {
+        self.saved.push(self.state.clone());
+    }
+
+    /// Pops the state saved last and makes it current, returning `false` if
+    /// there was none
+    pub fn restore(&mut self) -> bool {
+        match self.saved.pop() {
+            Some(state) => {
+                self.apply(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes `state` current, setting up the canvas for it
+    fn apply(&mut self, state: State) {
+        for _ in state.clips..self.state.clips {
+            self.canvas.pop_clip();
+        }
+        self.canvas.set_transform(state.transform);
+        self.canvas.set_stroke(state.stroke.clone());
+        self.canvas.set_blend_mode(state.blend_mode);
+        self.canvas.set_composite_mode(state.composite_mode);
+        self.canvas.set_antialias(state.antialias);
+        self.state = state;
+    }
+
+    /// Replaces the current transform
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.state.transform = transform;
+        self.canvas.set_transform(transform);
+    }
+
+    /// Current transform
+    pub fn transform(&self) -> Transform {
+        self.state.transform
+    }
+
+    /// Applies `transform` to the shapes drawn from now on, before the
+    /// current transform
+    pub fn apply_transform(&mut self, transform: &Transform) {
+        self.set_transform(self.state.transform.compose(transform));
+    }
+
+    /// Moves the shapes drawn from now on by `(tx, ty)`
+    pub fn translate(&mut self, tx: f32, ty: f32) {
+        self.set_transform(self.state.transform.translate(tx, ty));
+    }
+
+    /// Rotates the shapes drawn from now on by `angle` radians
+    pub fn rotate(&mut self, angle: f32) {
+        self.set_transform(self.state.transform.rotate(angle));
+    }
+
+    /// Scales the shapes drawn from now on by `sx` and `sy`
+    pub fn scale(&mut self, sx: f32, sy: f32) {
+        self.set_transform(self.state.transform.scale(sx, sy));
+    }
+
+    /// Skews the shapes drawn from now on, see [`Transform::skewing`]
+    pub fn skew(&mut self, angle_x: f32, angle_y: f32) {
+        self.set_transform(self.state.transform.skew(angle_x, angle_y));
+    }
+
+    /// Intersects the clip with the rectangle at `(x, y)` of size
+    /// `width x height`, given in the current coordinates. A rotated or
+    /// skewed rectangle clips to its bounding box.
+    pub fn clip(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let corners = [
+            (x, y),
+            (x + width, y),
+            (x + width, y + height),
+            (x, y + height),
+        ]
+        .map(|(x, y)| self.canvas.to_pixel(x, y));
+        let x_min = corners.iter().map(|p| p.0).fold(f32::INFINITY, f32::min);
+        let x_max = corners
+            .iter()
+            .map(|p| p.0)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let y_min = corners.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+        let y_max = corners
+            .iter()
+            .map(|p| p.1)
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        // pixels whose centers lie inside the rectangle
+        let (x0, x1) = (x_min.ceil() as i64, x_max.ceil() as i64);
+        let (y0, y1) = (y_min.ceil() as i64, y_max.ceil() as i64);
+        let clamp = |v: i64| v.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        self.canvas.push_clip(
+            clamp(x0),
+            clamp(y0),
+            (x1 - x0).max(0) as usize,
+            (y1 - y0).max(0) as usize,
+        );
+        self.state.clips += 1;
+    }
+
+    /// Sets the color shapes are filled with
+    pub fn set_fill_style(&mut self, color: Color) {
+        self.state.fill_style = color;
+    }
+
+    /// Color shapes are filled with
+    pub fn fill_style(&self) -> Color {
+        self.state.fill_style
+    }
+
+    /// Sets the color outlines are drawn with
+    pub fn set_stroke_style(&mut self, color: Color) {
+        self.state.stroke_style = color;
+    }
+
+    /// Color outlines are drawn with
+    pub fn stroke_style(&self) -> Color {
+        self.state.stroke_style
+    }
+
+    /// Sets the width of the outlines, see [`Canvas::set_line_width`]
+    pub fn set_line_width(&mut self, width: f32) {
+        self.canvas.set_line_width(width);
+        self.state.stroke = self.canvas.stroke().clone();
+    }
+
+    /// Width of the outlines
+    pub fn line_width(&self) -> f32 {
+        self.state.stroke.width
+    }
+
+    /// Sets the shape of the ends of open outlines
+    pub fn set_line_cap(&mut self, cap: LineCap) {
+        self.canvas.set_line_cap(cap);
+        self.state.stroke = self.canvas.stroke().clone();
+    }
+
+    /// Shape of the ends of open outlines
+    pub fn line_cap(&self) -> LineCap {
+        self.state.stroke.cap
+    }
+
+    /// Sets the shape of the corners of outlines
+    pub fn set_line_join(&mut self, join: LineJoin) {
+        self.canvas.set_line_join(join);
+        self.state.stroke = self.canvas.stroke().clone();
+    }
+
+    /// Shape of the corners of outlines
+    pub fn line_join(&self) -> LineJoin {
+        self.state.stroke.join
+    }
+
+    /// Sets the longest miter allowed, see [`Canvas::set_miter_limit`]
+    pub fn set_miter_limit(&mut self, limit: f32) {
+        self.canvas.set_miter_limit(limit);
+        self.state.stroke = self.canvas.stroke().clone();
+    }
+
+    /// Longest miter allowed, as a ratio of miter length to line width
+    pub fn miter_limit(&self) -> f32 {
+        self.state.stroke.miter_limit
+    }
+
+    /// Sets the dash pattern of outlines, see [`Canvas::set_line_dash`]
+    pub fn set_line_dash(&mut self, pattern: &[f32]) {
+        self.canvas.set_line_dash(pattern);
+        self.state.stroke = self.canvas.stroke().clone();
+    }
+
+    /// Dash pattern of outlines
+    pub fn line_dash(&self) -> &[f32] {
+        &self.state.stroke.dash
+    }
+
+    /// Sets how far into the dash pattern outlines start, see
+    /// [`Canvas::set_line_dash_offset`]
+    pub fn set_line_dash_offset(&mut self, offset: f32) {
+        self.canvas.set_line_dash_offset(offset);
+        self.state.stroke = self.canvas.stroke().clone();
+    }
+
+    /// Distance into the dash pattern at which outlines start
+    pub fn line_dash_offset(&self) -> f32 {
+        self.state.stroke.dash_offset
+    }
+
+    /// Sets how the colors are blended with the buffer
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.state.blend_mode = mode;
+        self.canvas.set_blend_mode(mode);
+    }
+
+    /// How the colors are blended with the buffer
+    pub fn blend_mode(&self) -> BlendMode {
+        self.state.blend_mode
+    }
+
+    /// Sets how the shapes are composited over the buffer
+    pub fn set_composite_mode(&mut self, mode: CompositeMode) {
+        self.state.composite_mode = mode;
+        self.canvas.set_composite_mode(mode);
+    }
+
+    /// How the shapes are composited over the buffer
+    pub fn composite_mode(&self) -> CompositeMode {
+        self.state.composite_mode
+    }
+
+    /// Enables or disables anti-aliasing
+    pub fn set_antialias(&mut self, antialias: bool) {
+        self.state.antialias = antialias;
+        self.canvas.set_antialias(antialias);
+    }
+
+    /// Whether anti-aliasing is enabled
+    pub fn is_antialiased(&self) -> bool {
+        self.state.antialias
+    }
+
+    /// Fills the shape with the fill color
+    pub fn fill(&mut self, shape: &dyn Shape) {
+        let color = self.state.fill_style;
+        shape.draw_filled(self.canvas, &color);
+    }
+
+    /// Draws the outline of the shape with the stroke color
+    pub fn stroke(&mut self, shape: &dyn Shape) {
+        let color = self.state.stroke_style;
+        shape.draw(self.canvas, &color);
+    }
+
+    /// Fills the shape if it is filled, strokes it otherwise
+    pub fn draw(&mut self, shape: &dyn Shape) {
+        if shape.is_filled() {
+            self.fill(shape)
+        } else {
+            self.stroke(shape)
+        }
+    }
+}
+
+impl Drop for Context<'_, '_> {
+    fn drop(&mut self) {
+        let initial = self.initial.clone();
+        self.apply(initial);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Rectangle2D;
+
+    #[test]
+    fn test_save_restore() {
+        let mut canvas = Canvas::new(20, 20).unwrap();
+        let empty = Some(Color::new(0, 0, 0, 0));
+        {
+            let mut ctx = Context::new(&mut canvas);
+            ctx.set_fill_style(color::RED);
+            ctx.set_line_width(3.0);
+
+            ctx.save();
+            ctx.translate(10.0, 10.0);
+            ctx.scale(2.0, 2.0);
+            ctx.clip(0.0, 0.0, 2.0, 2.0);
+            ctx.set_fill_style(color::BLUE);
+            ctx.set_line_width(1.0);
+            ctx.fill(&Rectangle2D::new(-5, -5, 10, 10, true));
+
+            assert!(ctx.restore());
+            assert_eq!(ctx.canvas().get_color(10, 10), Some(color::BLUE));
+            assert_eq!(ctx.canvas().get_color(13, 13), Some(color::BLUE));
+            assert_eq!(ctx.canvas().get_color(14, 14), empty);
+            assert_eq!(ctx.canvas().get_color(9, 9), empty);
+            assert!(!ctx.restore());
+            assert_eq!(ctx.fill_style(), color::RED);
+            assert_eq!(ctx.line_width(), 3.0);
+            assert!(ctx.transform().is_identity());
+
+            // the clip went away with the transform
+            ctx.fill(&Rectangle2D::new(0, 0, 2, 2, true));
+            assert_eq!(ctx.canvas().get_color(1, 1), Some(color::RED));
+        }
+
+        // the canvas is left as it was found
+        assert_eq!(canvas.line_width(), 1.0);
+        assert!(!canvas.pop_clip());
+    }
+
+    #[test]
+    fn test_nested_save() {
+        let mut canvas = Canvas::new(20, 20).unwrap();
+        let mut ctx = Context::new(&mut canvas);
+
+        ctx.set_blend_mode(BlendMode::Multiply);
+        ctx.save();
+        ctx.rotate(1.0);
+        ctx.set_stroke_style(color::GREEN);
+        ctx.save();
+        ctx.set_blend_mode(BlendMode::Screen);
+        ctx.set_line_dash(&[2.0, 1.0]);
+        ctx.set_line_dash_offset(1.5);
+        ctx.set_miter_limit(2.0);
+        assert_eq!(ctx.canvas().line_dash_offset(), 1.5);
+        assert_eq!(ctx.canvas().miter_limit(), 2.0);
+
+        assert!(ctx.restore());
+        assert_eq!(ctx.blend_mode(), BlendMode::Multiply);
+        assert_eq!(ctx.stroke_style(), color::GREEN);
+        assert!(ctx.line_dash().is_empty());
+        assert_eq!(ctx.line_dash_offset(), 0.0);
+        assert_eq!(ctx.miter_limit(), 10.0);
+        assert_eq!(ctx.canvas().blend_mode(), BlendMode::Multiply);
+        assert!(!ctx.canvas().stroke().is_dashed());
+        assert_eq!(ctx.canvas().line_dash_offset(), 0.0);
+        assert_eq!(ctx.canvas().miter_limit(), 10.0);
+
+        assert!(ctx.restore());
+        assert!(ctx.transform().is_identity());
+        assert!(ctx.canvas().transform().is_identity());
+    }
+}
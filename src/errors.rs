@@ -9,12 +9,30 @@ use super::Result;
 pub enum Error {
     /// Unable to init canvas with supplied buffer
     Unsupported(&'static str),
+    /// Malformed SVG path data, `position` being the byte offset in the data
+    /// where parsing failed
+    InvalidPath {
+        position: usize,
+        reason: &'static str,
+    },
+    /// Malformed or unsupported SVG document, `position` being the byte
+    /// offset in the document where loading failed
+    InvalidSvg {
+        position: usize,
+        reason: &'static str,
+    },
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            &Error::Unsupported(ref msg) => write!(f, "{}", msg),
+            Error::Unsupported(msg) => write!(f, "{}", msg),
+            Error::InvalidPath { position, reason } => {
+                write!(f, "invalid path data at {}: {}", position, reason)
+            }
+            Error::InvalidSvg { position, reason } => {
+                write!(f, "invalid svg at {}: {}", position, reason)
+            }
         }
     }
 }
@@ -22,7 +40,7 @@ impl fmt::Display for Error {
 impl std::error::Error for Error {
     fn cause(&self) -> Option<&dyn std::error::Error> {
         match *self {
-            Error::Unsupported(_) => None,
+            Error::Unsupported(_) | Error::InvalidPath { .. } | Error::InvalidSvg { .. } => None,
         }
     }
 }
@@ -31,3 +49,13 @@ impl std::error::Error for Error {
 pub fn unsupported_error<T>(msg: &'static str) -> Result<T> {
     Err(Error::Unsupported(msg))
 }
+
+/// function to create an invalid path error.
+pub fn invalid_path_error<T>(position: usize, reason: &'static str) -> Result<T> {
+    Err(Error::InvalidPath { position, reason })
+}
+
+/// function to create an invalid svg error.
+pub fn invalid_svg_error<T>(position: usize, reason: &'static str) -> Result<T> {
+    Err(Error::InvalidSvg { position, reason })
+}
@@ -4,6 +4,7 @@
 //! * Ellipse2D
 //! * Polygon2D
 //! * Bezier2D [Both quadratic and cubic]
+//! * Path2D [Lines and bezier curves combined]
 //! 
 //! # Example
 //! 
@@ -14,21 +15,18 @@
 //! const HEIGHT: usize = 512;
 //! 
 //! pub fn main {
-//!     // create a pixel buffer for RGBA values
-//!     let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
-//!     
-//!     // create canvas
+//!     // create canvas with its own buffer of RGBA pixels
 //!     let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 //!     
 //!     // draw line
-//!     shape::draw_line2d(50, 50, 200, 300, canvas, &ada::color::WHITE, &mut buffer[..]);
+//!     shape::draw_line2d(50, 50, 200, 300, &mut canvas, &ada::color::WHITE);
 //!     
 //!     // draw rectangle
-//!     shape::draw_rect2d(50, 100, 100, 150, canvas, &ada::color::RED, &mut buffer[..]); // hollow
-//!     shape::draw_rect2d_filled(50, 100, 90, 120, canvas, &ada::color::GREEN, &mut buffer[..]); // filled
+//!     shape::draw_rect2d(50, 100, 100, 150, &mut canvas, &ada::color::RED); // hollow
+//!     shape::draw_rect2d_filled(50, 100, 90, 120, &mut canvas, &ada::color::GREEN); // filled
 //!     
 //!     // draw circle
-//!     shape::draw_ellipse2d(350, 200, 100, 100, canvas, &ada::color::BLUE, &mut buffer[..]);
+//!     shape::draw_ellipse2d(350, 200, 100, 100, &mut canvas, &ada::color::BLUE);
 //! }
 //! ```
 
@@ -38,13 +36,24 @@
 #![feature(test)]
 extern crate test;
 
+mod backend;
 mod canvas;
 pub mod color;
+pub mod composite;
+mod context;
 pub mod errors;
+pub mod pixel;
 pub mod shape;
+pub mod svg;
+pub mod transform;
 
+pub use backend::Backend;
 pub use canvas::Canvas;
 pub use color::Color;
+pub use composite::{BlendMode, CompositeMode};
+pub use context::Context;
+pub use pixel::PixelFormat;
+pub use transform::Transform;
 
 /// A type for result generated by Ada
 pub type Result<T> = core::result::Result<T, errors::Error>;
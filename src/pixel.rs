@@ -0,0 +1,218 @@
+//! The `pixel` module defines the layouts of pixels in the buffers a canvas
+//! can draw into.
+//!
+//! Formats packed into a `u32` or `u16` store that integer in native endian
+//! byte order, so the buffer can be shared with APIs expecting `u32` pixels,
+//! like window framebuffers.
+
+use crate::Color;
+
+/// Layout of a pixel in the buffer
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 4 bytes per pixel, in the order red, green, blue, alpha
+    #[default]
+    Rgba8888,
+    /// 4 bytes per pixel, in the order blue, green, red, alpha
+    Bgra8888,
+    /// packed `u32` with the channels as `0xAARRGGBB`
+    Argb8888,
+    /// packed `u32` with the channels as `0x00RRGGBB`, the top byte being
+    /// unused. Pixels read back as opaque.
+    Xrgb8888,
+    /// 3 bytes per pixel, in the order red, green, blue. Pixels read back
+    /// as opaque.
+    Rgb888,
+    /// 1 byte of luminance per pixel. Pixels read back as opaque gray.
+    Gray8,
+    /// packed `u16` with 5 bits of red, 6 of green and 5 of blue, from the
+    /// most significant bit down. Pixels read back as opaque.
+    Rgb565,
+}
+
+impl PixelFormat {
+    /// Number of bytes taken by a pixel
+    #[inline]
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8888
+            | PixelFormat::Bgra8888
+            | PixelFormat::Argb8888
+            | PixelFormat::Xrgb8888 => 4,
+            PixelFormat::Rgb888 => 3,
+            PixelFormat::Gray8 => 1,
+            PixelFormat::Rgb565 => 2,
+        }
+    }
+
+    /// Whether the format keeps the alpha channel of the colors
+    #[inline]
+    pub fn has_alpha(self) -> bool {
+        matches!(
+            self,
+            PixelFormat::Rgba8888 | PixelFormat::Bgra8888 | PixelFormat::Argb8888
+        )
+    }
+
+    /// Encodes the color into `pixel`, which holds exactly one pixel
+    #[inline]
+    pub(crate) fn write(self, pixel: &mut [u8], color: &Color) {
+        match self {
+            PixelFormat::Rgba8888 => pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]),
+            PixelFormat::Bgra8888 => pixel.copy_from_slice(&[color.b, color.g, color.r, color.a]),
+            PixelFormat::Argb8888 => {
+                let packed = u32::from_be_bytes([color.a, color.r, color.g, color.b]);
+                pixel.copy_from_slice(&packed.to_ne_bytes());
+            }
+            PixelFormat::Xrgb8888 => {
+                let packed = u32::from_be_bytes([0, color.r, color.g, color.b]);
+                pixel.copy_from_slice(&packed.to_ne_bytes());
+            }
+            PixelFormat::Rgb888 => pixel.copy_from_slice(&[color.r, color.g, color.b]),
+            PixelFormat::Gray8 => pixel[0] = luminance(color),
+            PixelFormat::Rgb565 => {
+                let (r, g, b) = (color.r as u16, color.g as u16, color.b as u16);
+                let packed = ((r * 31 + 127) / 255) << 11
+                    | ((g * 63 + 127) / 255) << 5
+                    | ((b * 31 + 127) / 255);
+                pixel.copy_from_slice(&packed.to_ne_bytes());
+            }
+        }
+    }
+
+    /// Decodes the color stored in `pixel`, which holds exactly one pixel
+    #[inline]
+    pub(crate) fn read(self, pixel: &[u8]) -> Color {
+        match self {
+            PixelFormat::Rgba8888 => Color::new(pixel[0], pixel[1], pixel[2], pixel[3]),
+            PixelFormat::Bgra8888 => Color::new(pixel[2], pixel[1], pixel[0], pixel[3]),
+            PixelFormat::Argb8888 | PixelFormat::Xrgb8888 => {
+                let packed = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                let [a, r, g, b] = packed.to_be_bytes();
+                let a = if self == PixelFormat::Argb8888 {
+                    a
+                } else {
+                    255
+                };
+                Color::new(r, g, b, a)
+            }
+            PixelFormat::Rgb888 => Color::rgb(pixel[0], pixel[1], pixel[2]),
+            PixelFormat::Gray8 => Color::rgb(pixel[0], pixel[0], pixel[0]),
+            PixelFormat::Rgb565 => {
+                let packed = u16::from_ne_bytes([pixel[0], pixel[1]]);
+                let (r, g, b) = (packed >> 11, (packed >> 5) & 0x3f, packed & 0x1f);
+                Color::rgb(
+                    ((r * 255 + 15) / 31) as u8,
+                    ((g * 255 + 31) / 63) as u8,
+                    ((b * 255 + 15) / 31) as u8,
+                )
+            }
+        }
+    }
+}
+
+/// Luminance of the color using the Rec. 601 weights
+#[inline]
+fn luminance(color: &Color) -> u8 {
+    let (r, g, b) = (color.r as u32, color.g as u32, color.b as u32);
+    ((77 * r + 150 * g + 29 * b + 128) >> 8) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shape, Canvas};
+
+    const FORMATS: [PixelFormat; 7] = [
+        PixelFormat::Rgba8888,
+        PixelFormat::Bgra8888,
+        PixelFormat::Argb8888,
+        PixelFormat::Xrgb8888,
+        PixelFormat::Rgb888,
+        PixelFormat::Gray8,
+        PixelFormat::Rgb565,
+    ];
+
+    #[test]
+    fn test_layout() {
+        let color = Color::new(0x12, 0x34, 0x56, 0x78);
+        let mut pixel = [0u8; 4];
+
+        PixelFormat::Bgra8888.write(&mut pixel, &color);
+        assert_eq!(pixel, [0x56, 0x34, 0x12, 0x78]);
+
+        PixelFormat::Argb8888.write(&mut pixel, &color);
+        assert_eq!(u32::from_ne_bytes(pixel), 0x7812_3456);
+
+        PixelFormat::Xrgb8888.write(&mut pixel, &color);
+        assert_eq!(u32::from_ne_bytes(pixel), 0x0012_3456);
+
+        let mut pixel = [0u8; 2];
+        PixelFormat::Rgb565.write(&mut pixel, &Color::rgb(255, 0, 255));
+        assert_eq!(u16::from_ne_bytes(pixel), 0xf81f);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let colors = [
+            Color::new(0, 0, 0, 255),
+            Color::new(255, 255, 255, 255),
+            Color::new(255, 0, 0, 128),
+            Color::new(12, 200, 99, 7),
+        ];
+
+        for &format in FORMATS.iter() {
+            for color in colors.iter() {
+                let mut pixel = vec![0u8; format.bytes_per_pixel()];
+                format.write(&mut pixel, color);
+                let read = format.read(&pixel);
+
+                let expected = match format {
+                    PixelFormat::Gray8 => {
+                        let l = luminance(color);
+                        Color::rgb(l, l, l)
+                    }
+                    _ if !format.has_alpha() => Color::rgb(color.r, color.g, color.b),
+                    _ => *color,
+                };
+                // 565 keeps only the top 5 or 6 bits of each channel
+                let tolerance = if format == PixelFormat::Rgb565 { 4 } else { 0 };
+                let close = |a: u8, b: u8| (a as i32 - b as i32).abs() <= tolerance;
+                assert!(
+                    close(read.r, expected.r)
+                        && close(read.g, expected.g)
+                        && close(read.b, expected.b)
+                        && read.a == expected.a,
+                    "{:?}: {:?} read back as {:?}",
+                    format,
+                    color,
+                    read
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_canvas_formats() {
+        for &format in FORMATS.iter() {
+            let mut canvas = Canvas::with_format(4, 4, format).unwrap();
+            canvas.clear(&Color::rgb(0, 0, 255));
+
+            // translucent color is blended with the pixels read back
+            let red = Color::new(255, 0, 0, 128);
+            shape::draw_rect2d_filled(1, 1, 2, 2, &mut canvas, &red);
+
+            let expected = match format {
+                PixelFormat::Gray8 => Color::rgb(53, 53, 53),
+                PixelFormat::Rgb565 => Color::rgb(132, 0, 123),
+                _ => Color::rgb(128, 0, 127),
+            };
+            assert_eq!(canvas.get_color(1, 1), Some(expected), "{:?}", format);
+            let blue = match format {
+                PixelFormat::Gray8 => Color::rgb(29, 29, 29),
+                _ => Color::rgb(0, 0, 255),
+            };
+            assert_eq!(canvas.get_color(0, 0), Some(blue));
+        }
+    }
+}
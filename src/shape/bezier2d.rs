@@ -1,7 +1,8 @@
-use super::line2d::draw_line2d;
+use super::stroke::{stroke_polyline, LineJoin, Point};
 use crate::canvas::Canvas;
-use crate::shape::Shape;
+use crate::shape::{Shape, Target};
 use crate::Color;
+use core::f32::consts::SQRT_2;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct QuadraticBezier2D {
@@ -21,12 +22,12 @@ impl QuadraticBezier2D {
 }
 
 impl Shape for QuadraticBezier2D {
-    fn draw(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_quadratic_bezier2d(self.start, self.end, self.control, canvas, color, buffer);
+    fn draw(&self, target: &mut dyn Target, color: &Color) {
+        target.quadratic_bezier(self.start, self.control, self.end, color);
     }
 
-    fn draw_filled(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_quadratic_bezier2d(self.start, self.end, self.control, canvas, color, buffer);
+    fn draw_filled(&self, target: &mut dyn Target, color: &Color) {
+        self.draw(target, color);
     }
 
     fn is_filled(&self) -> bool {
@@ -59,79 +60,48 @@ impl CubicBezier2D {
 }
 
 impl Shape for CubicBezier2D {
-    fn draw(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_cubic_bezier2d(
-            self.start,
-            self.end,
-            self.control_a,
-            self.control_b,
-            canvas,
-            color,
-            buffer,
-        );
+    fn draw(&self, target: &mut dyn Target, color: &Color) {
+        target.cubic_bezier(self.start, self.control_a, self.control_b, self.end, color);
     }
 
-    fn draw_filled(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_cubic_bezier2d(
-            self.start,
-            self.end,
-            self.control_a,
-            self.control_b,
-            canvas,
-            color,
-            buffer,
-        );
+    fn draw_filled(&self, target: &mut dyn Target, color: &Color) {
+        self.draw(target, color);
     }
+
     fn is_filled(&self) -> bool {
         false
     }
 }
 
 /// Draws the Quadratic Bezier Curve using function from https://pomax.github.io/bezierinfo/#control
+///
+/// The curve is drawn using the line width of the canvas, anti-aliased if it
+/// is enabled on the canvas.
 pub fn draw_quadratic_bezier2d(
     start: (i32, i32),
     end: (i32, i32),
     control: (i32, i32),
     canvas: &mut Canvas,
     color: &Color,
-    buffer: &mut [u8],
 ) {
-    let quadratic_bezier_curve = |t: f32| {
-        let t2 = t * t;
-        let mt = 1.0 - t;
-        let mt2 = mt * mt;
-        let x = (start.0 as f32 * mt2) + (2.0 * control.0 as f32 * mt * t) + (end.0 as f32 * t2);
-        let y = (start.1 as f32 * mt2) + (2.0 * control.1 as f32 * mt * t) + (end.1 as f32 * t2);
-        // round to nearest pixel, to avoid ugly line artifacts
-        (x.round() as i32, y.round() as i32)
-    };
-
-    let distance = |point_a: (i32, i32), point_b: (i32, i32)| {
-        (((point_a.0 - point_b.0).pow(2) + (point_a.1 - point_b.1).pow(2)) as f32).sqrt()
-    };
-
-    // Approximate curve's length by adding distance between control points.
-    let curve_length_bound: f32 = distance(start, control) + distance(control, end);
-
-    // Use hyperbola function to give shorter curves a bias in number of line
-    // segments.
-    let num_segments: i32 = ((curve_length_bound.powi(2) + 800.0).sqrt() / 8.0) as i32;
+    let start = canvas.map_point_f32(start.0 as f32, start.1 as f32);
+    let end = canvas.map_point_f32(end.0 as f32, end.1 as f32);
+    let control = canvas.map_point_f32(control.0 as f32, control.1 as f32);
 
-    // Sample points along the curve and connect them with line segments.
-    let t_interval = 1f32 / (num_segments as f32);
-    let mut t1 = 0f32;
-    let mut s1 = quadratic_bezier_curve(t1);
-    for i in 0..num_segments {
-        let t2 = (i as f32 + 1.0) * t_interval;
-        let s2 = quadratic_bezier_curve(t2);
-        draw_line2d(s1.0, s1.1, s2.0, s2.1, canvas, color, buffer);
-        t1 = t2;
-        s1 = quadratic_bezier_curve(t1);
+    // curve lies inside the convex hull of its control points
+    if !control_box_visible(&[start, end, control], canvas) {
+        return;
     }
+
+    let points = flatten_quadratic(start, control, end, stroked_area(canvas));
+    stroke_polyline(&points, false, canvas, color);
 }
 
 /// Draws the Cubic Bezier Curve using function from https://pomax.github.io/bezierinfo/#control
 ///
+/// The curve is drawn using the line width of the canvas, anti-aliased if it
+/// is enabled on the canvas.
+///
 /// Source Code is taken from [imageproc library](https://github.com/image-rs/imageproc/blob/master/src/drawing/bezier.rs)
 pub fn draw_cubic_bezier2d(
     start: (i32, i32),
@@ -140,46 +110,208 @@ pub fn draw_cubic_bezier2d(
     control_b: (i32, i32),
     canvas: &mut Canvas,
     color: &Color,
-    buffer: &mut [u8],
 ) {
-    let cubic_bezier_curve = |t: f32| {
-        let t2 = t * t;
-        let t3 = t2 * t;
-        let mt = 1.0 - t;
-        let mt2 = mt * mt;
-        let mt3 = mt2 * mt;
-        let x = (start.0 as f32 * mt3)
-            + (3.0 * control_a.0 as f32 * mt2 * t)
-            + (3.0 * control_b.0 as f32 * mt * t2)
-            + (end.0 as f32 * t3);
-        let y = (start.1 as f32 * mt3)
-            + (3.0 * control_a.1 as f32 * mt2 * t)
-            + (3.0 * control_b.1 as f32 * mt * t2)
-            + (end.1 as f32 * t3);
-        // round to nearest pixel, to avoid ugly line artifacts
-        (x.round() as i32, y.round() as i32)
-    };
+    let start = canvas.map_point_f32(start.0 as f32, start.1 as f32);
+    let end = canvas.map_point_f32(end.0 as f32, end.1 as f32);
+    let control_a = canvas.map_point_f32(control_a.0 as f32, control_a.1 as f32);
+    let control_b = canvas.map_point_f32(control_b.0 as f32, control_b.1 as f32);
+
+    // curve lies inside the convex hull of its control points
+    if !control_box_visible(&[start, end, control_a, control_b], canvas) {
+        return;
+    }
+
+    let points = flatten_cubic(start, control_a, control_b, end, stroked_area(canvas));
+    stroke_polyline(&points, false, canvas, color);
+}
 
-    let distance = |point_a: (i32, i32), point_b: (i32, i32)| {
-        (((point_a.0 - point_b.0).pow(2) + (point_a.1 - point_b.1).pow(2)) as f32).sqrt()
+/// Rectangle `(x_min, y_min, x_max, y_max)` in pixels outside of which the
+/// parts of a curve are not sampled
+pub(crate) type Area = (f32, f32, f32, f32);
+
+/// Curves whose control points are further than this many pixels apart are
+/// split in halves before being sampled
+const SPLIT_LENGTH: f32 = 128.0;
+
+/// Splits deeper than this are sampled as they are
+const MAX_SPLIT_DEPTH: u32 = 32;
+
+/// Area of the canvas the stroke of a curve can paint, its clip grown by the
+/// reach of the joins and caps.
+///
+/// Dashes are measured along the whole curve, so dashed outlines are sampled
+/// over their full length and no area is returned for them.
+pub(crate) fn stroked_area(canvas: &Canvas) -> Option<Area> {
+    let stroke = canvas.stroke();
+    if stroke.is_dashed() {
+        return None;
+    }
+    let reach = match stroke.join {
+        LineJoin::Miter => stroke.miter_limit.max(SQRT_2),
+        _ => SQRT_2,
     };
+    Some(grown_clip(
+        canvas,
+        stroke.width.max(1.0) / 2.0 * reach + 1.0,
+    ))
+}
+
+/// Area of the canvas the fill of a path can paint
+pub(crate) fn filled_area(canvas: &Canvas) -> Area {
+    grown_clip(canvas, 1.0)
+}
+
+fn grown_clip(canvas: &Canvas, margin: f32) -> Area {
+    let (x_min, y_min, x_max, y_max) = canvas.clip_bounds();
+    (
+        x_min as f32 - margin,
+        y_min as f32 - margin,
+        x_max as f32 + margin,
+        y_max as f32 + margin,
+    )
+}
 
+/// Samples the quadratic curve into the points of a polyline, from `start`
+/// to `end`.
+///
+/// Long curves are split in halves until they are short enough to sample.
+/// Halves lying outside `area` are replaced by the line joining their ends,
+/// which stays inside their control box and so outside the area too, so a
+/// huge curve crossing the canvas only costs its visible length.
+pub(crate) fn flatten_quadratic(
+    start: Point,
+    control: Point,
+    end: Point,
+    area: Option<Area>,
+) -> Vec<Point> {
+    let mut points = vec![start];
+    sample_curve([start, control, end], area, 0, &mut points);
+    points
+}
+
+/// Samples the cubic curve into the points of a polyline, from `start` to
+/// `end`, leaving out the parts outside `area` as [`flatten_quadratic`] does
+pub(crate) fn flatten_cubic(
+    start: Point,
+    control_a: Point,
+    control_b: Point,
+    end: Point,
+    area: Option<Area>,
+) -> Vec<Point> {
+    let mut points = vec![start];
+    sample_curve([start, control_a, control_b, end], area, 0, &mut points);
+    points
+}
+
+/// Appends the points sampled along the quadratic or cubic curve given by
+/// its control points, except the first one, to `points`
+fn sample_curve<const N: usize>(
+    curve: [Point; N],
+    area: Option<Area>,
+    depth: u32,
+    points: &mut Vec<Point>,
+) {
     // Approximate curve's length by adding distance between control points.
-    let curve_length_bound: f32 =
-        distance(start, control_a) + distance(control_a, control_b) + distance(control_b, end);
+    let curve_length_bound: f32 = curve.windows(2).map(|w| distance(w[0], w[1])).sum();
+
+    if let Some(area) = area {
+        let (x_min, y_min, x_max, y_max) = control_box(&curve);
+        if x_max < area.0 || x_min > area.2 || y_max < area.1 || y_min > area.3 {
+            points.push(curve[N - 1]);
+            return;
+        }
+    }
 
-    let num_segments: i32 = ((curve_length_bound.powi(2) + 800.0).sqrt() / 8.0) as i32;
+    // the halves only depend on the length of the curve, not on the area,
+    // so the visible parts are sampled the same whatever the clip
+    if depth < MAX_SPLIT_DEPTH && curve_length_bound > SPLIT_LENGTH {
+        let (first, second) = split_curve(curve);
+        sample_curve(first, area, depth + 1, points);
+        sample_curve(second, area, depth + 1, points);
+        return;
+    }
+
+    let num_segments = segment_count(curve_length_bound);
 
     // Sample points along the curve and connect them with line segments.
     let t_interval = 1f32 / (num_segments as f32);
-    let mut t1 = 0f32;
-    for i in 0..num_segments {
-        let t2 = (i as f32 + 1.0) * t_interval;
-        let s1 = cubic_bezier_curve(t1);
-        let s2 = cubic_bezier_curve(t2);
-        draw_line2d(s1.0, s1.1, s2.0, s2.1, canvas, color, buffer);
-        t1 = t2;
+    points.extend((1..=num_segments).map(|i| point_at(&curve, i as f32 * t_interval)));
+}
+
+/// Point at `t` of the quadratic or cubic curve
+fn point_at(curve: &[Point], t: f32) -> Point {
+    let t2 = t * t;
+    let mt = 1.0 - t;
+    let mt2 = mt * mt;
+    if let [start, control, end] = *curve {
+        let x = (start.0 * mt2) + (2.0 * control.0 * mt * t) + (end.0 * t2);
+        let y = (start.1 * mt2) + (2.0 * control.1 * mt * t) + (end.1 * t2);
+        return (x, y);
+    }
+
+    let (start, control_a, control_b, end) = (curve[0], curve[1], curve[2], curve[3]);
+    let t3 = t2 * t;
+    let mt3 = mt2 * mt;
+    let x = (start.0 * mt3)
+        + (3.0 * control_a.0 * mt2 * t)
+        + (3.0 * control_b.0 * mt * t2)
+        + (end.0 * t3);
+    let y = (start.1 * mt3)
+        + (3.0 * control_a.1 * mt2 * t)
+        + (3.0 * control_b.1 * mt * t2)
+        + (end.1 * t3);
+    (x, y)
+}
+
+/// Splits the curve in two halves using de Casteljau's algorithm
+fn split_curve<const N: usize>(curve: [Point; N]) -> ([Point; N], [Point; N]) {
+    let (mut first, mut second, mut points) = (curve, curve, curve);
+    for i in 0..N {
+        first[i] = points[0];
+        second[N - 1 - i] = points[N - 1 - i];
+        for j in 0..N - 1 - i {
+            points[j] = (
+                (points[j].0 + points[j + 1].0) / 2.0,
+                (points[j].1 + points[j + 1].1) / 2.0,
+            );
+        }
     }
+    (first, second)
+}
+
+/// Bounding box `(x_min, y_min, x_max, y_max)` of the points
+fn control_box(points: &[Point]) -> Area {
+    let x_min = points.iter().map(|p| p.0).fold(f32::INFINITY, f32::min);
+    let x_max = points.iter().map(|p| p.0).fold(f32::NEG_INFINITY, f32::max);
+    let y_min = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+    let y_max = points.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+    (x_min, y_min, x_max, y_max)
+}
+
+#[inline]
+fn distance(point_a: Point, point_b: Point) -> f32 {
+    (point_a.0 - point_b.0).hypot(point_a.1 - point_b.1)
+}
+
+/// Number of line segments for a curve of about `length` pixels. The
+/// hyperbola function gives shorter curves a bias in number of segments.
+#[inline]
+fn segment_count(length: f32) -> i32 {
+    ((length.powi(2) + 800.0).sqrt() / 8.0) as i32
+}
+
+/// Checks if the bounding box of the control points, grown by the stroke
+/// width, overlaps the canvas
+fn control_box_visible(points: &[Point], canvas: &Canvas) -> bool {
+    let margin = (canvas.stroke().width / 2.0).ceil();
+    let (x_min, y_min, x_max, y_max) = control_box(points);
+
+    canvas.is_visible(
+        (x_min - margin).floor() as i64,
+        (y_min - margin).floor() as i64,
+        (x_max + margin).ceil() as i64,
+        (y_max + margin).ceil() as i64,
+    )
 }
 
 #[cfg(test)]
@@ -191,26 +323,108 @@ mod tests {
     const WIDTH: usize = 512;
     const HEIGHT: usize = 512;
 
+    #[test]
+    fn test_huge_curve_crossing_canvas() {
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        let area = stroked_area(&canvas);
+
+        // straight cubic along row 50, about two million pixels long
+        let (start, end) = ((-1_000_000, 50), (1_000_000, 50));
+        let (control_a, control_b) = ((-300_000, 50), (300_000, 50));
+        let points = flatten_cubic((-1e6, 50.0), (-3e5, 50.0), (3e5, 50.0), (1e6, 50.0), area);
+        assert!(points.len() < 200);
+        assert_eq!(points[0], (-1e6, 50.0));
+        assert_eq!(points[points.len() - 1], (1e6, 50.0));
+
+        draw_cubic_bezier2d(start, end, control_a, control_b, &mut canvas, &color::WHITE);
+        for x in 0..100 {
+            assert_eq!(canvas.get_color(x, 50), Some(color::WHITE));
+            assert_eq!(canvas.get_color(x, 49), Some(Color::from([0, 0, 0, 0])));
+        }
+
+        // parabola whose apex is the center of the canvas
+        let (start, control, end) = (
+            (-999_950, 1_000_000),
+            (50, -999_900),
+            (1_000_050, 1_000_000),
+        );
+        let points = flatten_quadratic(
+            (-999_950.0, 1e6),
+            (50.0, -999_900.0),
+            (1_000_050.0, 1e6),
+            area,
+        );
+        assert!(points.len() < 200);
+
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        draw_quadratic_bezier2d(start, end, control, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(50, 50), Some(color::WHITE));
+        assert_eq!(canvas.get_color(50, 52), Some(Color::from([0, 0, 0, 0])));
+    }
+
+    #[test]
+    fn test_visible_curve_sampled_fully() {
+        // curves inside the area are sampled as without one, the long ones
+        // being split the same way
+        let (start, control_a, control_b, end) =
+            ((110.0, 150.0), (25.0, 190.0), (210.0, 250.0), (210.0, 30.0));
+        let canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        assert_eq!(
+            flatten_cubic(start, control_a, control_b, end, stroked_area(&canvas)),
+            flatten_cubic(start, control_a, control_b, end, None)
+        );
+    }
+
+    #[test]
+    fn test_clip_keeps_pixels_inside() {
+        // long curves are sampled the same whatever the clip, which only
+        // leaves out the pixels outside of it
+        let draw = |canvas: &mut Canvas| {
+            let cubic = CubicBezier2D::new((10, 390), (390, 10), (600, 300), (-200, 100));
+            let quadratic = QuadraticBezier2D::new((-50, 20), (450, 380), (300, -100));
+            canvas.draw(&cubic, &color::WHITE);
+            canvas.draw(&quadratic, &Color::new(255, 0, 0, 128));
+        };
+        for &(width, antialias) in [(1.0, false), (1.0, true), (5.0, false)].iter() {
+            let mut full = Canvas::new(400, 400).unwrap();
+            full.set_line_width(width);
+            full.set_antialias(antialias);
+            draw(&mut full);
+
+            for &(x, y, w, h) in [(100, 100, 150, 150), (0, 150, 400, 60), (200, 0, 30, 400)].iter()
+            {
+                let mut clipped = Canvas::new(400, 400).unwrap();
+                clipped.set_line_width(width);
+                clipped.set_antialias(antialias);
+                clipped.push_clip(x, y, w, h);
+                draw(&mut clipped);
+
+                for py in 0..400 {
+                    for px in 0..400 {
+                        let inside = px >= x && px < x + w as i32 && py >= y && py < y + h as i32;
+                        let expected = if inside {
+                            full.get_color(px, py)
+                        } else {
+                            Some(Color::from([0, 0, 0, 0]))
+                        };
+                        assert_eq!(clipped.get_color(px, py), expected, "{} {}", px, py);
+                    }
+                }
+            }
+        }
+    }
+
     #[bench]
     fn bench_render_quadratic_bezier(b: &mut Bencher) {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
         b.iter(|| {
-            draw_quadratic_bezier2d(
-                (10, 500),
-                (500, 10),
-                (40, 40),
-                &mut canvas,
-                &color::WHITE,
-                &mut buffer[..],
-            )
+            draw_quadratic_bezier2d((10, 500), (500, 10), (40, 40), &mut canvas, &color::WHITE)
         });
     }
 
     #[bench]
     fn bench_render_cubic_bezier(b: &mut Bencher) {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
         b.iter(|| {
@@ -221,7 +435,6 @@ mod tests {
                 (210, 250),
                 &mut canvas,
                 &color::WHITE,
-                &mut buffer[..],
             )
         });
     }
@@ -1,6 +1,7 @@
-use super::line2d::draw_line2d;
+use super::scanline::{fill_contours, FillRule};
+use super::stroke::stroke_path;
 use crate::canvas::Canvas;
-use crate::shape::Shape;
+use crate::shape::{Shape, Target};
 use crate::Color;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -25,29 +26,16 @@ impl Ellipse2D {
 }
 
 impl Shape for Ellipse2D {
-    fn draw(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_ellipse2d(
-            self.xc,
-            self.yc,
-            self.width_radius,
-            self.height_radius,
-            canvas,
-            color,
-            buffer,
-        );
+    fn draw(&self, target: &mut dyn Target, color: &Color) {
+        let (center, radii) = ((self.xc, self.yc), (self.width_radius, self.height_radius));
+        target.ellipse(center, radii, false, color);
     }
 
-    fn draw_filled(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_ellipse2d_filled(
-            self.xc,
-            self.yc,
-            self.width_radius,
-            self.height_radius,
-            canvas,
-            color,
-            buffer,
-        );
+    fn draw_filled(&self, target: &mut dyn Target, color: &Color) {
+        let (center, radii) = ((self.xc, self.yc), (self.width_radius, self.height_radius));
+        target.ellipse(center, radii, true, color);
     }
+
     fn is_filled(&self) -> bool {
         self.is_filled
     }
@@ -58,6 +46,10 @@ impl Shape for Ellipse2D {
 /// For circle which is just a special case when major and minor axis are equal,
 /// we move to more optimized [Midpoint Circle Algorithm](https://en.wikipedia.org/wiki/Midpoint_circle_algorithm).
 /// It exploits eight the fold symmetry in circles.
+///
+/// If the line width of the canvas is more than a pixel, the ring between the
+/// ellipses grown and shrunk by half the width is filled instead. Dashed
+/// outlines are stroked along a polygon approximating the ellipse.
 pub fn draw_ellipse2d(
     xc: i32,
     yc: i32,
@@ -65,30 +57,89 @@ pub fn draw_ellipse2d(
     height_radius: i32,
     canvas: &mut Canvas,
     color: &Color,
-    buffer: &mut [u8],
 ) {
+    if !canvas.is_axis_aligned() {
+        draw_ellipse_polygon(
+            xc,
+            yc,
+            width_radius,
+            height_radius,
+            false,
+            false,
+            canvas,
+            color,
+        );
+        return;
+    }
+    let (xc, yc) = canvas.map_point(xc, yc);
+    let (width_radius, height_radius) = canvas.map_radii(width_radius, height_radius);
+    draw_ellipse_outline(xc, yc, width_radius, height_radius, canvas, color);
+}
+
+/// Draws the ellipse outline, the center and radii being in pixels
+fn draw_ellipse_outline(
+    xc: i32,
+    yc: i32,
+    width_radius: i32,
+    height_radius: i32,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    if canvas.stroke().is_dashed() {
+        let points = flatten_axis_ellipse(xc, yc, width_radius, height_radius);
+        stroke_path(&points, true, false, canvas, color);
+        return;
+    }
+    if !canvas.stroke().is_thin() {
+        draw_ellipse_ring(xc, yc, width_radius, height_radius, canvas, color);
+        return;
+    }
+
     // if major axis length and minor axis length is same, means it is a circle
     // circle have eight fold symmetry, so we can use more optimized algorithm
     if width_radius == height_radius {
-        draw_circle(xc, yc, width_radius, canvas, color, buffer);
+        draw_circle(xc, yc, width_radius, canvas, color);
+        return;
+    }
+
+    let (xc64, yc64) = (xc as i64, yc as i64);
+    let (a, b) = (width_radius as i64, height_radius as i64);
+    // skip the quadrants lying outside the canvas
+    let quadrants = [
+        canvas.is_visible(xc64 - a, yc64, xc64, yc64 + b),
+        canvas.is_visible(xc64, yc64, xc64 + a, yc64 + b),
+        canvas.is_visible(xc64 - a, yc64 - b, xc64, yc64),
+        canvas.is_visible(xc64, yc64 - b, xc64 + a, yc64),
+    ];
+    if !quadrants.iter().any(|&q| q) {
         return;
     }
 
-    let wr2 = width_radius * width_radius;
-    let hr2 = height_radius * height_radius;
-    let mut x = -width_radius;
+    // the error terms grow with the square of the radii, so they are kept
+    // in i64 for large ellipses
+    let (wr2, hr2) = (a * a, b * b);
+    let mut x = -a;
     let mut y = 0;
-    let mut e2 = height_radius;
-    let mut dx = (1 + 2 * x) * e2 * e2;
+    let mut dx = (1 + 2 * x) * hr2;
     let mut dy = x * x;
     let mut err = dx + dy;
 
     while x <= 0 {
-        canvas.draw_point(xc + x, yc + y, color, buffer);
-        canvas.draw_point(xc - x, yc + y, color, buffer);
-        canvas.draw_point(xc + x, yc - y, color, buffer);
-        canvas.draw_point(xc - x, yc - y, color, buffer);
-        e2 = 2 * err;
+        let (px, py) = (x as i32, y);
+        // points on the axes are shared by two quadrants
+        if quadrants[0] {
+            canvas.draw_point(xc + px, yc + py, color);
+        }
+        if quadrants[1] && x != 0 {
+            canvas.draw_point(xc - px, yc + py, color);
+        }
+        if quadrants[2] && y != 0 {
+            canvas.draw_point(xc + px, yc - py, color);
+        }
+        if quadrants[3] && x != 0 && y != 0 {
+            canvas.draw_point(xc - px, yc - py, color);
+        }
+        let e2 = 2 * err;
         if e2 >= dx {
             x += 1;
             dx += 2 * hr2;
@@ -104,34 +155,54 @@ pub fn draw_ellipse2d(
 
     while y < height_radius {
         y += 1;
-        canvas.draw_point(xc, yc + y, color, buffer);
-        canvas.draw_point(xc, yc - y, color, buffer);
+        canvas.draw_point(xc, yc + y, color);
+        canvas.draw_point(xc, yc - y, color);
     }
 }
 
 /// Draws the circle using [Midpoint Circle Algorithm](https://en.wikipedia.org/wiki/Midpoint_circle_algorithm)
+///
+/// Octants lying fully outside the canvas are skipped, so a huge circle
+/// placed off the canvas costs nothing.
 #[inline(always)]
-fn draw_circle(
-    xc: i32,
-    yc: i32,
-    radius: i32,
-    canvas: &mut Canvas,
-    color: &Color,
-    buffer: &mut [u8],
-) {
+fn draw_circle(xc: i32, yc: i32, radius: i32, canvas: &mut Canvas, color: &Color) {
+    let octants = visible_octants(xc, yc, radius, canvas);
+    if !octants.iter().any(|&o| o) {
+        return;
+    }
+
     let mut x = 0i32;
     let mut y = radius;
     let mut d = 1 - radius;
 
     while x <= y {
-        canvas.draw_point(xc + x, yc + y, color, buffer);
-        canvas.draw_point(xc + y, yc + x, color, buffer);
-        canvas.draw_point(xc - y, yc + x, color, buffer);
-        canvas.draw_point(xc - x, yc + y, color, buffer);
-        canvas.draw_point(xc - x, yc - y, color, buffer);
-        canvas.draw_point(xc - y, yc - x, color, buffer);
-        canvas.draw_point(xc + y, yc - x, color, buffer);
-        canvas.draw_point(xc + x, yc - y, color, buffer);
+        // neighbouring octants share their points on the axes, where x is
+        // zero, and on the diagonals, where x equals y
+        let (axis, diagonal) = (x == 0, x == y);
+        if octants[0] {
+            canvas.draw_point(xc + x, yc + y, color);
+        }
+        if octants[1] && !diagonal {
+            canvas.draw_point(xc + y, yc + x, color);
+        }
+        if octants[2] && !diagonal {
+            canvas.draw_point(xc - y, yc + x, color);
+        }
+        if octants[3] && !axis {
+            canvas.draw_point(xc - x, yc + y, color);
+        }
+        if octants[4] && y != 0 {
+            canvas.draw_point(xc - x, yc - y, color);
+        }
+        if octants[5] && !axis && !diagonal {
+            canvas.draw_point(xc - y, yc - x, color);
+        }
+        if octants[6] && !axis && !diagonal {
+            canvas.draw_point(xc + y, yc - x, color);
+        }
+        if octants[7] && !axis {
+            canvas.draw_point(xc + x, yc - y, color);
+        }
 
         x += 1;
         if d < 0 {
@@ -143,6 +214,149 @@ fn draw_circle(
     }
 }
 
+/// Fills the ring of a thick ellipse outline row by row. A pixel belongs to
+/// the ring if its center lies inside the outer ellipse and not strictly
+/// inside the inner one.
+fn draw_ellipse_ring(
+    xc: i32,
+    yc: i32,
+    width_radius: i32,
+    height_radius: i32,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    let half_width = canvas.stroke().width / 2.0;
+    let (a_out, b_out) = (
+        width_radius as f32 + half_width,
+        height_radius as f32 + half_width,
+    );
+    let (a_in, b_in) = (
+        width_radius as f32 - half_width,
+        height_radius as f32 - half_width,
+    );
+    let (xc64, yc64) = (xc as i64, yc as i64);
+    let (reach_x, reach_y) = (a_out.floor() as i64, b_out.floor() as i64);
+    if !canvas.is_visible(
+        xc64 - reach_x,
+        yc64 - reach_y,
+        xc64 + reach_x,
+        yc64 + reach_y,
+    ) {
+        return;
+    }
+
+    // half width of the ellipse with radii a and b at vertical offset dy
+    let span = |a: f32, b: f32, dy: f32| a * (1.0 - (dy * dy) / (b * b)).max(0.0).sqrt();
+
+    let (clip_x_min, clip_y_min, clip_x_max, clip_y_max) = canvas.clip_bounds();
+    // span ends are computed in i64 and clamped to the clip, as the ring of a
+    // large ellipse reaches past the range of i32
+    let clamp = |x: i64| x.clamp(clip_x_min as i64 - 1, clip_x_max as i64 + 1) as i32;
+    let y_start = (yc64 - reach_y).max(clip_y_min as i64) as i32;
+    let y_end = (yc64 + reach_y).min(clip_y_max as i64) as i32;
+    for y in y_start..=y_end {
+        let dy = (y as i64 - yc64) as f32;
+        let outer = span(a_out, b_out, dy).floor() as i64;
+        let inner = if a_in > 0.0 && b_in > 0.0 && dy.abs() < b_in {
+            span(a_in, b_in, dy).ceil() as i64
+        } else {
+            0
+        };
+
+        if inner == 0 {
+            canvas.draw_span(clamp(xc64 - outer), clamp(xc64 + outer + 1), y, color);
+        } else if inner <= outer {
+            canvas.draw_span(clamp(xc64 - outer), clamp(xc64 - inner + 1), y, color);
+            canvas.draw_span(clamp(xc64 + inner), clamp(xc64 + outer + 1), y, color);
+        }
+    }
+}
+
+/// Finds which octants of the circle overlap the canvas, in the order the
+/// midpoint algorithm plots them.
+fn visible_octants(xc: i32, yc: i32, radius: i32, canvas: &Canvas) -> [bool; 8] {
+    let (xc, yc, r) = (xc as i64, yc as i64, radius as i64);
+    // along an octant one offset stays in [0, near] and the other one in
+    // [far, r], with a pixel of slack for rounding
+    let diagonal = radius as f64 * core::f64::consts::FRAC_1_SQRT_2;
+    let near = diagonal.ceil() as i64 + 1;
+    let far = (diagonal.floor() as i64 - 1).max(0);
+
+    [
+        canvas.is_visible(xc, yc + far, xc + near, yc + r),
+        canvas.is_visible(xc + far, yc, xc + r, yc + near),
+        canvas.is_visible(xc - r, yc, xc - far, yc + near),
+        canvas.is_visible(xc - near, yc + far, xc, yc + r),
+        canvas.is_visible(xc - near, yc - r, xc, yc - far),
+        canvas.is_visible(xc - r, yc - near, xc - far, yc),
+        canvas.is_visible(xc + far, yc - near, xc + r, yc),
+        canvas.is_visible(xc, yc - r, xc + near, yc - far),
+    ]
+}
+
+/// Polygon approximating the ellipse centered on `center`, whose radii are
+/// the vectors `u` and `v`, with vertices about a pixel apart. It starts at
+/// the end of `u` and goes towards the end of `v`.
+fn flatten_ellipse(center: (f32, f32), u: (f32, f32), v: (f32, f32)) -> Vec<(f32, f32)> {
+    let radius = u.0.hypot(u.1).max(v.0.hypot(v.1));
+    let count = (core::f32::consts::TAU * radius).ceil().clamp(8.0, 4096.0) as usize;
+
+    (0..count)
+        .map(|i| {
+            let angle = i as f32 / count as f32 * core::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+            (
+                center.0 + u.0 * cos + v.0 * sin,
+                center.1 + u.1 * cos + v.1 * sin,
+            )
+        })
+        .collect()
+}
+
+/// Polygon approximating the axis aligned ellipse, starting at its rightmost
+/// point and going clockwise on screen
+fn flatten_axis_ellipse(
+    xc: i32,
+    yc: i32,
+    width_radius: i32,
+    height_radius: i32,
+) -> Vec<(f32, f32)> {
+    flatten_ellipse(
+        (xc as f32, yc as f32),
+        (width_radius as f32, 0.0),
+        (0.0, height_radius as f32),
+    )
+}
+
+/// Draws the ellipse as a polygon once mapped to pixels, for canvases whose
+/// transform rotates or skews it
+#[allow(clippy::too_many_arguments)]
+fn draw_ellipse_polygon(
+    xc: i32,
+    yc: i32,
+    width_radius: i32,
+    height_radius: i32,
+    filled: bool,
+    antialias: bool,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    // the ellipse is mapped to the one spanned by the images of its radii
+    let (x, y) = (xc as f32, yc as f32);
+    let center = canvas.map_point_f32(x, y);
+    let u = canvas.map_point_f32(x + width_radius as f32, y);
+    let v = canvas.map_point_f32(x, y + height_radius as f32);
+    let u = (u.0 - center.0, u.1 - center.1);
+    let v = (v.0 - center.0, v.1 - center.1);
+
+    let points = flatten_ellipse(center, u, v);
+    if filled {
+        fill_contours(Some(&points[..]), FillRule::NonZero, canvas, color);
+    } else {
+        stroke_path(&points, true, antialias, canvas, color);
+    }
+}
+
 pub fn draw_ellipse2d_filled(
     xc: i32,
     yc: i32,
@@ -150,26 +364,64 @@ pub fn draw_ellipse2d_filled(
     height_radius: i32,
     canvas: &mut Canvas,
     color: &Color,
-    buffer: &mut [u8],
 ) {
+    if !canvas.is_axis_aligned() {
+        draw_ellipse_polygon(
+            xc,
+            yc,
+            width_radius,
+            height_radius,
+            true,
+            false,
+            canvas,
+            color,
+        );
+        return;
+    }
+    let (xc, yc) = canvas.map_point(xc, yc);
+    let (width_radius, height_radius) = canvas.map_radii(width_radius, height_radius);
+    draw_ellipse_fill(xc, yc, width_radius, height_radius, canvas, color);
+}
+
+/// Fills the ellipse, the center and radii being in pixels
+fn draw_ellipse_fill(
+    xc: i32,
+    yc: i32,
+    width_radius: i32,
+    height_radius: i32,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    let (a, b) = (width_radius as i64, height_radius as i64);
+    if !canvas.is_visible(xc as i64 - a, yc as i64 - b, xc as i64 + a, yc as i64 + b) {
+        return;
+    }
+
     if width_radius == height_radius {
-        draw_circle_filled(xc, yc, width_radius, canvas, color, buffer);
+        draw_circle_filled(xc, yc, width_radius, canvas, color);
         return;
     }
 
-    let wr2 = width_radius * width_radius;
-    let hr2 = height_radius * height_radius;
-    let mut x = -width_radius;
+    // error terms in i64 as for the outline
+    let (wr2, hr2) = (a * a, b * b);
+    let mut x = -a;
     let mut y = 0;
-    let mut e2 = height_radius;
-    let mut dx = (1 + 2 * x) * e2 * e2;
+    let mut dx = (1 + 2 * x) * hr2;
     let mut dy = x * x;
     let mut err = dx + dy;
 
+    // x only grows, so the first span found on a row is the widest
+    let mut row = -1;
     while x <= 0 {
-        draw_line2d(xc + x, yc + y, xc - x, yc + y, canvas, color, buffer);
-        draw_line2d(xc + x, yc - y, xc - x, yc - y, canvas, color, buffer);
-        e2 = 2 * err;
+        if y != row {
+            let (px, py) = (x as i32, y);
+            canvas.draw_span(xc + px, xc - px + 1, yc + py, color);
+            if y != 0 {
+                canvas.draw_span(xc + px, xc - px + 1, yc - py, color);
+            }
+            row = y;
+        }
+        let e2 = 2 * err;
         if e2 >= dx {
             x += 1;
             dx += 2 * hr2;
@@ -185,28 +437,44 @@ pub fn draw_ellipse2d_filled(
 
     while y < height_radius - 1 {
         y += 1;
-        draw_line2d(xc, yc - y, xc, yc + y, canvas, color, buffer);
+        canvas.draw_point(xc, yc - y, color);
+        canvas.draw_point(xc, yc + y, color);
     }
 }
 
-#[inline(always)]
-fn draw_circle_filled(
-    xc: i32,
-    yc: i32,
-    radius: i32,
-    canvas: &mut Canvas,
-    color: &Color,
-    buffer: &mut [u8],
-) {
+/// Fills the circle with the rows spanned by the points of the midpoint
+/// algorithm, each row being drawn once.
+fn draw_circle_filled(xc: i32, yc: i32, radius: i32, canvas: &mut Canvas, color: &Color) {
+    if radius < 0 {
+        return;
+    }
+
+    // only the rows inside the clip are computed, their offsets from the
+    // center going from the distance to the clip to its farthest row
+    let (_, clip_y_min, _, clip_y_max) = canvas.clip_bounds();
+    let (y_min, y_max, yc64) = (clip_y_min as i64, clip_y_max as i64, yc as i64);
+    let first = (y_min - yc64).max(yc64 - y_max).max(0);
+    let last = (y_max - yc64).max(yc64 - y_min).min(radius as i64);
+    if first > last {
+        return;
+    }
+    let (first, last) = (first as i32, last as i32);
+
+    // half width of the row at each of these offsets
+    let mut half_widths = vec![0; (last - first) as usize + 1];
+    let mut record = |dy: i32, half_width: i32| {
+        if (first..=last).contains(&dy) {
+            let i = (dy - first) as usize;
+            half_widths[i] = half_widths[i].max(half_width);
+        }
+    };
     let mut x = 0i32;
     let mut y = radius;
     let mut d = 1 - radius;
 
     while x <= y {
-        draw_line2d(xc + x, yc + y, xc - x, yc + y, canvas, color, buffer);
-        draw_line2d(xc + y, yc + x, xc - y, yc + x, canvas, color, buffer);
-        draw_line2d(xc + y, yc - x, xc - y, yc - x, canvas, color, buffer);
-        draw_line2d(xc + x, yc - y, xc - x, yc - y, canvas, color, buffer);
+        record(y, x);
+        record(x, y);
 
         x += 1;
         if d < 0 {
@@ -216,6 +484,210 @@ fn draw_circle_filled(
             d += 2 * (x - y) + 5;
         }
     }
+
+    for (i, &half_width) in half_widths.iter().enumerate() {
+        let dy = first + i as i32;
+        canvas.draw_span(xc - half_width, xc + half_width + 1, yc + dy, color);
+        if dy != 0 {
+            canvas.draw_span(xc - half_width, xc + half_width + 1, yc - dy, color);
+        }
+    }
+}
+
+/// Draws an anti-aliased ellipse outline.
+///
+/// Coverage of each pixel near the outline is computed from its signed
+/// distance to the ellipse, and the color is alpha blended into the buffer
+/// with that coverage. Circles use the exact distance, other ellipses use the
+/// first order approximation `f / |grad f|` of their implicit equation.
+///
+/// If the line width of the canvas is more than a pixel, the ring of
+/// [`draw_ellipse2d`] is filled instead, without anti-aliasing.
+pub fn draw_ellipse2d_aa(
+    xc: i32,
+    yc: i32,
+    width_radius: i32,
+    height_radius: i32,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    if !canvas.is_axis_aligned() {
+        draw_ellipse_polygon(
+            xc,
+            yc,
+            width_radius,
+            height_radius,
+            false,
+            true,
+            canvas,
+            color,
+        );
+        return;
+    }
+    let (xc, yc) = canvas.map_point(xc, yc);
+    let (width_radius, height_radius) = canvas.map_radii(width_radius, height_radius);
+    if width_radius <= 0 || height_radius <= 0 {
+        draw_ellipse_outline(xc, yc, width_radius, height_radius, canvas, color);
+        return;
+    }
+    if canvas.stroke().is_dashed() {
+        let points = flatten_axis_ellipse(xc, yc, width_radius, height_radius);
+        stroke_path(&points, true, true, canvas, color);
+        return;
+    }
+    if !canvas.stroke().is_thin() {
+        draw_ellipse_ring(xc, yc, width_radius, height_radius, canvas, color);
+        return;
+    }
+    draw_ellipse_coverage(xc, yc, width_radius, height_radius, false, canvas, color);
+}
+
+/// Draws an anti-aliased filled ellipse.
+///
+/// Pixels fully inside the ellipse are filled, the ones on the edge are alpha
+/// blended with their coverage, computed as in [`draw_ellipse2d_aa`].
+pub fn draw_ellipse2d_filled_aa(
+    xc: i32,
+    yc: i32,
+    width_radius: i32,
+    height_radius: i32,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    if !canvas.is_axis_aligned() {
+        draw_ellipse_polygon(
+            xc,
+            yc,
+            width_radius,
+            height_radius,
+            true,
+            true,
+            canvas,
+            color,
+        );
+        return;
+    }
+    let (xc, yc) = canvas.map_point(xc, yc);
+    let (width_radius, height_radius) = canvas.map_radii(width_radius, height_radius);
+    if width_radius <= 0 || height_radius <= 0 {
+        draw_ellipse_fill(xc, yc, width_radius, height_radius, canvas, color);
+        return;
+    }
+    draw_ellipse_coverage(xc, yc, width_radius, height_radius, true, canvas, color);
+}
+
+/// Signed distance of the offset `(x, y)` from the center to the outline of
+/// the ellipse with radii `a` and `b`, negative inside.
+#[inline]
+fn ellipse_distance(x: f32, y: f32, a: f32, b: f32) -> f32 {
+    if a == b {
+        return (x * x + y * y).sqrt() - a;
+    }
+    let f = (x * x) / (a * a) + (y * y) / (b * b) - 1.0;
+    let gx = 2.0 * x / (a * a);
+    let gy = 2.0 * y / (b * b);
+    let g = (gx * gx + gy * gy).sqrt();
+    if g == 0.0 {
+        return -a.min(b);
+    }
+    f / g
+}
+
+/// Walks each row of the ellipse outwards and inwards from the point where
+/// it crosses the outline, blending pixels while their coverage is partial.
+///
+/// The distance grows with the horizontal offset along a row, so the walk
+/// can stop at the first pixel with no coverage, or full coverage when
+/// filling, in which case the rest of the row is filled directly.
+#[allow(clippy::too_many_arguments)]
+fn draw_ellipse_coverage(
+    xc: i32,
+    yc: i32,
+    width_radius: i32,
+    height_radius: i32,
+    filled: bool,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    let (a, b) = (width_radius as f32, height_radius as f32);
+    let (xc64, yc64) = (xc as i64, yc as i64);
+    let (reach_x, reach_y) = (width_radius as i64 + 2, height_radius as i64 + 2);
+    if !canvas.is_visible(
+        xc64 - reach_x,
+        yc64 - reach_y,
+        xc64 + reach_x,
+        yc64 + reach_y,
+    ) {
+        return;
+    }
+
+    let coverage = |dx: i64, dy: f32| {
+        let d = ellipse_distance(dx as f32, dy, a, b);
+        if filled {
+            (0.5 - d).clamp(0.0, 1.0)
+        } else {
+            (1.0 - d.abs()).max(0.0)
+        }
+    };
+
+    // offsets are in i64 and the pixels clipped before going back to i32,
+    // as the ellipse may reach past the range of i32
+    let (clip_x_min, clip_y_min, clip_x_max, clip_y_max) = canvas.clip_bounds();
+    let (x_min, x_max) = (clip_x_min as i64, clip_x_max as i64);
+    // blends the pixel at horizontal offset dx on both sides of the center
+    let plot = |dx: i64, y: i32, c: f32, canvas: &mut Canvas| {
+        let mut blend = |x: i64| {
+            if (x_min..=x_max).contains(&x) {
+                canvas.blend_point(x as i32, y, color, c);
+            }
+        };
+        blend(xc64 + dx);
+        if dx != 0 {
+            blend(xc64 - dx);
+        }
+    };
+
+    // the walks only go through the offsets of pixels inside the clip, from
+    // the distance to the clip to its farthest column
+    let first = (x_min - xc64).max(xc64 - x_max).max(0);
+    let last = (x_max - xc64).max(xc64 - x_min);
+
+    let y_start = (yc64 - reach_y).max(clip_y_min as i64) as i32;
+    let y_end = (yc64 + reach_y).min(clip_y_max as i64) as i32;
+    for y in y_start..=y_end {
+        let dy = (y as i64 - yc64) as f32;
+        let crossing = if dy.abs() < b {
+            a * (1.0 - (dy * dy) / (b * b)).sqrt()
+        } else {
+            0.0
+        };
+        let start = crossing.round() as i64;
+
+        let mut outer = start.max(first);
+        while outer <= last {
+            let c = coverage(outer, dy);
+            if c <= 0.0 {
+                break;
+            }
+            plot(outer, y, c, canvas);
+            outer += 1;
+        }
+
+        let mut inner = (start - 1).min(last);
+        while inner >= first {
+            let c = coverage(inner, dy);
+            if (filled && c >= 1.0) || (!filled && c <= 0.0) {
+                break;
+            }
+            plot(inner, y, c, canvas);
+            inner -= 1;
+        }
+
+        if filled {
+            let clamp = |x: i64| x.clamp(x_min - 1, x_max + 1) as i32;
+            canvas.draw_span(clamp(xc64 - inner), clamp(xc64 + inner + 1), y, color);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -227,75 +699,236 @@ mod tests {
     const WIDTH: usize = 512;
     const HEIGHT: usize = 512;
 
+    #[test]
+    fn test_circle_partially_outside() {
+        let mut canvas = Canvas::new(20, 20).unwrap();
+
+        draw_ellipse2d(0, 0, 10, 10, &mut canvas, &color::WHITE);
+
+        let white = [255, 255, 255, 255];
+        assert_eq!(canvas.get_color(10, 0), Some(Color::from(white)));
+        assert_eq!(canvas.get_color(0, 10), Some(Color::from(white)));
+        assert_eq!(canvas.get_color(7, 6), Some(Color::from(white)));
+    }
+
+    #[test]
+    fn test_circle_aa_coverage() {
+        let mut canvas = Canvas::new(40, 40).unwrap();
+        canvas.clear(&color::BLACK);
+
+        draw_ellipse2d_aa(20, 20, 10, 10, &mut canvas, &color::WHITE);
+
+        let black = [0, 0, 0, 255];
+        let white = [255, 255, 255, 255];
+        // pixels exactly on the circle are fully covered
+        assert_eq!(canvas.get_color(30, 20), Some(Color::from(white)));
+        assert_eq!(canvas.get_color(20, 10), Some(Color::from(white)));
+        assert_eq!(canvas.get_color(20, 20), Some(Color::from(black)));
+        assert_eq!(canvas.get_color(32, 20), Some(Color::from(black)));
+        // (27, 27) lies ~0.1 inside the circle
+        let c = canvas.get_color(27, 27).unwrap();
+        assert!(c.r > 200 && c.r < 255);
+        // (28, 27) lies ~0.6 outside the circle
+        let c = canvas.get_color(28, 27).unwrap();
+        assert!(c.r > 50 && c.r < 150);
+    }
+
+    #[test]
+    fn test_ellipse_filled_aa_coverage() {
+        let mut canvas = Canvas::new(40, 40).unwrap();
+        canvas.clear(&color::BLACK);
+
+        draw_ellipse2d_filled_aa(20, 20, 15, 8, &mut canvas, &color::WHITE);
+
+        let black = [0, 0, 0, 255];
+        let white = [255, 255, 255, 255];
+        let half = [128, 128, 128, 255];
+        assert_eq!(canvas.get_color(20, 20), Some(Color::from(white)));
+        assert_eq!(canvas.get_color(34, 20), Some(Color::from(white)));
+        assert_eq!(canvas.get_color(20, 27), Some(Color::from(white)));
+        // pixel centers exactly on the outline are half covered
+        assert_eq!(canvas.get_color(35, 20), Some(Color::from(half)));
+        assert_eq!(canvas.get_color(20, 12), Some(Color::from(half)));
+        assert_eq!(canvas.get_color(36, 20), Some(Color::from(black)));
+        assert_eq!(canvas.get_color(20, 29), Some(Color::from(black)));
+    }
+
+    #[test]
+    fn test_circle_thick_ring() {
+        let mut canvas = Canvas::new(40, 40).unwrap();
+        canvas.set_line_width(4.0);
+
+        draw_ellipse2d(20, 20, 10, 10, &mut canvas, &color::WHITE);
+
+        let white = [255, 255, 255, 255];
+        let empty = [0, 0, 0, 0];
+        for &(x, y) in [(28, 20), (30, 20), (32, 20), (20, 8), (20, 32), (12, 20)].iter() {
+            assert_eq!(canvas.get_color(x, y), Some(Color::from(white)));
+        }
+        for &(x, y) in [(20, 20), (27, 20), (33, 20), (20, 7), (20, 13)].iter() {
+            assert_eq!(canvas.get_color(x, y), Some(Color::from(empty)));
+        }
+    }
+
+    #[test]
+    fn test_thick_ellipse_aa() {
+        // anti-aliasing keeps the width of thick outlines
+        let mut ring = Canvas::new(60, 60).unwrap();
+        ring.set_line_width(8.0);
+        ring.draw(&Ellipse2D::new(30, 30, 20, 14, false), &color::WHITE);
+
+        let mut canvas = Canvas::new(60, 60).unwrap();
+        canvas.set_line_width(8.0);
+        canvas.set_antialias(true);
+        canvas.draw(&Ellipse2D::new(30, 30, 20, 14, false), &color::WHITE);
+
+        assert_eq!(canvas.bytes(), ring.bytes());
+        assert_eq!(canvas.get_color(53, 30), Some(color::WHITE));
+        assert_eq!(canvas.get_color(47, 30), Some(color::WHITE));
+    }
+
+    #[test]
+    fn test_circle_dashed() {
+        let mut solid = Canvas::new(40, 40).unwrap();
+        solid.set_line_width(3.0);
+        draw_ellipse2d(20, 20, 12, 12, &mut solid, &color::WHITE);
+
+        let mut canvas = Canvas::new(40, 40).unwrap();
+        canvas.set_line_width(3.0);
+        canvas.set_line_dash(&[6.0, 6.0]);
+        draw_ellipse2d(20, 20, 12, 12, &mut canvas, &color::WHITE);
+
+        // about half of the ring is left out, the first dash starting at the
+        // rightmost point and going clockwise
+        let count = |canvas: &Canvas| {
+            let buffer = canvas.bytes().unwrap();
+            buffer.chunks(4).filter(|p| p[3] != 0).count()
+        };
+        let (solid, dashed) = (count(&solid), count(&canvas));
+        assert!(dashed * 10 > solid * 4 && dashed * 10 < solid * 6);
+
+        let white = [255, 255, 255, 255];
+        let empty = [0, 0, 0, 0];
+        assert_eq!(canvas.get_color(32, 23), Some(Color::from(white)));
+        assert_eq!(canvas.get_color(29, 28), Some(Color::from(empty)));
+    }
+
+    #[test]
+    fn test_ellipse_far_outside() {
+        let mut canvas = Canvas::new(20, 20).unwrap();
+
+        draw_ellipse2d(-5000, 10, 100, 100, &mut canvas, &color::WHITE);
+        draw_ellipse2d(10, -5000, 100, 50, &mut canvas, &color::WHITE);
+        draw_ellipse2d_filled(10, 5000, 100, 50, &mut canvas, &color::WHITE);
+
+        assert!(canvas.bytes().unwrap().iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_ellipse_large_radii() {
+        // only the leftmost part of the ellipse lies on the canvas
+        let white = Some(color::WHITE);
+        let empty = Some(Color::from([0, 0, 0, 0]));
+
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        draw_ellipse2d(1500, 50, 1500, 1000, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(0, 50), white);
+        assert_eq!(canvas.get_color(1, 50), empty);
+
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        draw_ellipse2d_filled(1500, 50, 1500, 1000, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(0, 50), white);
+        assert_eq!(canvas.get_color(99, 99), white);
+        assert_eq!(canvas.get_color(0, 0), empty);
+        assert_eq!(canvas.get_color(5, 0), white);
+
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        canvas.set_line_width(4.0);
+        draw_ellipse2d(1500, 50, 1500, 1000, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(2, 50), white);
+        assert_eq!(canvas.get_color(3, 50), empty);
+
+        // huge radii centered on the canvas cover all of it
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        draw_ellipse2d_filled(0, 0, 150_000, 100_000, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(99, 99), white);
+    }
+
+    #[test]
+    fn test_circle_filled_large_radius() {
+        // only the bottom rows of the circle reach the canvas
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        draw_ellipse2d_filled(50, -99_950, 100_000, 100_000, &mut canvas, &color::WHITE);
+
+        let white = Some(color::WHITE);
+        let empty = Some(Color::from([0, 0, 0, 0]));
+        assert_eq!(canvas.get_color(0, 0), white);
+        assert_eq!(canvas.get_color(99, 50), white);
+        assert_eq!(canvas.get_color(50, 51), empty);
+    }
+
+    #[test]
+    fn test_ellipse_aa_near_i32_limits() {
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        canvas.set_antialias(true);
+        canvas.draw(
+            &Ellipse2D::new(i32::MAX - 5, 50, 30, 20, false),
+            &color::WHITE,
+        );
+        canvas.draw(
+            &Ellipse2D::new(i32::MIN + 5, 50, 30, 20, true),
+            &color::WHITE,
+        );
+        assert!(canvas.bytes().unwrap().iter().all(|&b| b == 0));
+
+        // the left end of the ellipse reaches the canvas, the right one lies
+        // past i32::MAX
+        for &filled in [false, true].iter() {
+            let huge = Ellipse2D::new(i32::MAX - 5, 50, i32::MAX - 10, 20, filled);
+            canvas.draw(&huge, &color::WHITE);
+        }
+
+        // partly visible, the left half being clipped
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        canvas.set_antialias(true);
+        canvas.draw(&Ellipse2D::new(-10, 50, 30, 20, true), &color::WHITE);
+        assert_eq!(canvas.get_color(0, 50), Some(color::WHITE));
+        assert_eq!(canvas.get_color(19, 50), Some(color::WHITE));
+        assert_eq!(canvas.get_color(22, 50), Some(Color::from([0, 0, 0, 0])));
+    }
+
     #[bench]
     fn bench_render_circle(b: &mut Bencher) {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        b.iter(|| {
-            draw_ellipse2d(
-                200,
-                200,
-                200,
-                200,
-                &mut canvas,
-                &color::WHITE,
-                &mut buffer[..],
-            )
-        });
+        b.iter(|| draw_ellipse2d(200, 200, 200, 200, &mut canvas, &color::WHITE));
     }
 
     #[bench]
     fn bench_render_ellipse(b: &mut Bencher) {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        b.iter(|| {
-            draw_ellipse2d(
-                200,
-                200,
-                199,
-                200,
-                &mut canvas,
-                &color::WHITE,
-                &mut buffer[..],
-            )
-        });
+        b.iter(|| draw_ellipse2d(200, 200, 199, 200, &mut canvas, &color::WHITE));
     }
 
     #[bench]
     fn bench_render_circle_filled(b: &mut Bencher) {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        b.iter(|| {
-            draw_ellipse2d_filled(
-                200,
-                200,
-                200,
-                200,
-                &mut canvas,
-                &color::WHITE,
-                &mut buffer[..],
-            )
-        });
+        b.iter(|| draw_ellipse2d_filled(200, 200, 200, 200, &mut canvas, &color::WHITE));
     }
 
     #[bench]
     fn bench_render_ellipse_filled(b: &mut Bencher) {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        b.iter(|| {
-            draw_ellipse2d_filled(
-                200,
-                200,
-                199,
-                200,
-                &mut canvas,
-                &color::WHITE,
-                &mut buffer[..],
-            )
-        });
+        b.iter(|| draw_ellipse2d_filled(200, 200, 199, 200, &mut canvas, &color::WHITE));
+    }
+
+    #[bench]
+    fn bench_render_ellipse_filled_wide(b: &mut Bencher) {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+
+        b.iter(|| draw_ellipse2d_filled(256, 256, 255, 100, &mut canvas, &color::WHITE));
     }
 }
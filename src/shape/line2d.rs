@@ -1,5 +1,6 @@
+use super::stroke::stroke_path;
 use crate::canvas::Canvas;
-use crate::shape::Shape;
+use crate::shape::{Shape, Target};
 use crate::Color;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -17,12 +18,12 @@ impl Line2D {
 }
 
 impl Shape for Line2D {
-    fn draw(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_line2d(self.x1, self.y1, self.x2, self.y2, canvas, color, buffer);
+    fn draw(&self, target: &mut dyn Target, color: &Color) {
+        target.line((self.x1, self.y1), (self.x2, self.y2), color);
     }
 
-    fn draw_filled(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_line2d(self.x1, self.y1, self.x2, self.y2, canvas, color, buffer);
+    fn draw_filled(&self, target: &mut dyn Target, color: &Color) {
+        self.draw(target, color);
     }
 
     fn is_filled(&self) -> bool {
@@ -33,24 +34,73 @@ impl Shape for Line2D {
 /// Draws the line using [Bresenham's line Algorithm](https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm)
 ///
 /// It only involves integer calculations hence is fast than DDA
-pub fn draw_line2d(
+///
+/// If the line width of the canvas is more than a pixel, the line is filled
+/// as a quad instead. Dashed lines are drawn dash by dash.
+pub fn draw_line2d(x1: i32, y1: i32, x2: i32, y2: i32, canvas: &mut Canvas, color: &Color) {
+    let stroke = canvas.stroke();
+    if stroke.is_thin() && !stroke.is_dashed() {
+        let (x1, y1) = canvas.map_point(x1, y1);
+        let (x2, y2) = canvas.map_point(x2, y2);
+        draw_line2d_bresenham(x1, y1, x2, y2, canvas, color);
+    } else {
+        let points = [
+            canvas.map_point_f32(x1 as f32, y1 as f32),
+            canvas.map_point_f32(x2 as f32, y2 as f32),
+        ];
+        stroke_path(&points, false, false, canvas, color);
+    }
+}
+
+/// One pixel wide Bresenham line.
+///
+/// The line is clipped against the canvas before rasterizing. Lines lying
+/// fully on one side of the canvas are rejected using Cohen-Sutherland
+/// outcodes, the rest are clipped parametrically in the spirit of
+/// Liang-Barsky, by solving for the range of Bresenham steps which fall
+/// inside. The visible pixels are exactly the ones of the unclipped line.
+pub(crate) fn draw_line2d_bresenham(
     x1: i32,
     y1: i32,
     x2: i32,
     y2: i32,
     canvas: &mut Canvas,
     color: &Color,
-    buffer: &mut [u8],
 ) {
-    let mut mx1 = x1;
-    let mut mx2 = x2;
-    let mut my1 = y1;
-    let mut my2 = y2;
+    let bounds = canvas.clip_bounds();
+    bresenham_pixels(x1, y1, x2, y2, bounds, |x, y| {
+        canvas.draw_point(x, y, color)
+    });
+}
+
+/// Calls `plot` with each pixel of the Bresenham line which lies inside the
+/// inclusive `bounds`, see [`draw_line2d_bresenham`].
+fn bresenham_pixels<F: FnMut(i32, i32)>(
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+    bounds: (i32, i32, i32, i32),
+    mut plot: F,
+) {
+    if outcode(x1, y1, bounds) & outcode(x2, y2, bounds) != 0 {
+        return;
+    }
+
+    let (mut mx1, mut my1, mut mx2, mut my2) = (x1 as i64, y1 as i64, x2 as i64, y2 as i64);
+    let (mut u_min, mut v_min, mut u_max, mut v_max) = (
+        bounds.0 as i64,
+        bounds.1 as i64,
+        bounds.2 as i64,
+        bounds.3 as i64,
+    );
     let mut steep = false;
 
     if (mx1 - mx2).abs() < (my1 - my2).abs() {
         std::mem::swap(&mut mx1, &mut my1);
         std::mem::swap(&mut mx2, &mut my2);
+        std::mem::swap(&mut u_min, &mut v_min);
+        std::mem::swap(&mut u_max, &mut v_max);
         steep = true;
     }
     if mx1 > mx2 {
@@ -59,22 +109,232 @@ pub fn draw_line2d(
     }
     let dx = mx2 - mx1;
     let derror = ((my2 - my1) * 2).abs();
-    let mut error = 0;
-    let mut y = my1;
-    for x in mx1..(mx2 + 1) {
+    let step = if my2 > my1 { 1 } else { -1 };
+
+    // Before plotting the k-th pixel the minor axis has been stepped
+    // n(k) = floor((k * derror + dx - 1) / (2 * dx)) times. Find the range of
+    // k keeping both axes inside the clip bounds.
+    let mut k_start = (u_min - mx1).max(0);
+    let mut k_end = (u_max - mx1).min(dx);
+    if dx > 0 {
+        // allowed range for number of minor axis steps
+        let (n_min, n_max) = if step > 0 {
+            (v_min - my1, v_max - my1)
+        } else {
+            (my1 - v_max, my1 - v_min)
+        };
+        if n_max < 0 {
+            return;
+        }
+        if derror == 0 {
+            if n_min > 0 {
+                return;
+            }
+        } else {
+            if n_min > 0 {
+                let first = 2 * dx * n_min - dx + 1;
+                k_start = k_start.max((first + derror - 1) / derror);
+            }
+            k_end = k_end.min((2 * dx * (n_max + 1) - dx) / derror);
+        }
+    }
+
+    let steps = if dx > 0 {
+        (k_start * derror + dx - 1) / (2 * dx)
+    } else {
+        0
+    };
+    let mut error = k_start * derror - 2 * dx * steps;
+    let mut y = my1 + step * steps;
+
+    for x in (mx1 + k_start)..(mx1 + k_end + 1) {
         if steep {
-            canvas.draw_point(y, x, color, buffer);
+            plot(y as i32, x as i32);
         } else {
-            canvas.draw_point(x, y, color, buffer);
+            plot(x as i32, y as i32);
         }
         error += derror;
         if error > dx {
-            y += if my2 > my1 { 1 } else { -1 };
+            y += step;
             error -= dx * 2;
         }
     }
 }
 
+/// Draws an anti-aliased line using [Xiaolin Wu's line Algorithm](https://en.wikipedia.org/wiki/Xiaolin_Wu%27s_line_algorithm)
+///
+/// Every pixel gets a coverage depending on its distance from the ideal line,
+/// and the color is alpha blended into the buffer with that coverage.
+///
+/// If the line width of the canvas is more than a pixel, the line is filled
+/// as a quad instead, without anti-aliasing.
+pub fn draw_line2d_aa(x1: i32, y1: i32, x2: i32, y2: i32, canvas: &mut Canvas, color: &Color) {
+    let (x1, y1) = canvas.map_point_f32(x1 as f32, y1 as f32);
+    let (x2, y2) = canvas.map_point_f32(x2 as f32, y2 as f32);
+    let stroke = canvas.stroke();
+    if !stroke.is_thin() || stroke.is_dashed() {
+        stroke_path(&[(x1, y1), (x2, y2)], false, true, canvas, color);
+        return;
+    }
+    draw_line2d_wu(x1, y1, x2, y2, canvas, color);
+}
+
+/// Wu's line algorithm on sub-pixel end points.
+pub(crate) fn draw_line2d_wu(
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    let bounds = canvas.clip_bounds();
+    wu_pixels(x1, y1, x2, y2, bounds, |x, y, coverage| {
+        canvas.blend_point(x, y, color, coverage)
+    });
+}
+
+/// Calls `plot` with the pixels of the Wu line and their coverage, skipping
+/// the line if it lies outside of the inclusive `bounds`.
+fn wu_pixels<F: FnMut(i32, i32, f32)>(
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    bounds: (i32, i32, i32, i32),
+    mut plot: F,
+) {
+    // a pixel of slack for the neighbouring pixel on the minor axis
+    let visible = x1.max(x2).ceil() as i64 + 1 >= bounds.0 as i64
+        && x1.min(x2).floor() as i64 - 1 <= bounds.2 as i64
+        && y1.max(y2).ceil() as i64 + 1 >= bounds.1 as i64
+        && y1.min(y2).floor() as i64 - 1 <= bounds.3 as i64;
+    if !visible {
+        return;
+    }
+
+    let (mut x1, mut y1, mut x2, mut y2) = (x1, y1, x2, y2);
+    let steep = (y2 - y1).abs() > (x2 - x1).abs();
+    if steep {
+        std::mem::swap(&mut x1, &mut y1);
+        std::mem::swap(&mut x2, &mut y2);
+    }
+    if x1 > x2 {
+        std::mem::swap(&mut x1, &mut x2);
+        std::mem::swap(&mut y1, &mut y2);
+    }
+    let dx = x2 - x1;
+    // only a single point is left with dx == 0 once steep lines are swapped
+    let gradient = if dx == 0.0 { 0.0 } else { (y2 - y1) / dx };
+
+    let (u_min, u_max) = if steep {
+        (bounds.1, bounds.3)
+    } else {
+        (bounds.0, bounds.2)
+    };
+    let mut plot = |x: i32, y: i32, coverage: f32| {
+        if steep {
+            plot(y, x, coverage);
+        } else {
+            plot(x, y, coverage);
+        }
+    };
+    let fpart = |v: f32| v - v.floor();
+    let rfpart = |v: f32| 1.0 - fpart(v);
+
+    // first end point
+    let xend = x1.round();
+    let yend = y1 + gradient * (xend - x1);
+    let xgap = rfpart(x1 + 0.5);
+    let xpxl1 = xend as i32;
+    let ypxl1 = yend.floor() as i32;
+    let first = [
+        (ypxl1, rfpart(yend) * xgap),
+        (ypxl1 + 1, fpart(yend) * xgap),
+    ];
+
+    // second end point
+    let xend = x2.round();
+    let yend = y2 + gradient * (xend - x2);
+    let xgap = fpart(x2 + 0.5);
+    let xpxl2 = xend as i32;
+    let ypxl2 = yend.floor() as i32;
+    let second = [
+        (ypxl2, rfpart(yend) * xgap),
+        (ypxl2 + 1, fpart(yend) * xgap),
+    ];
+
+    if xpxl1 == xpxl2 {
+        // both end points fall in the same column, whose pixels are plotted
+        // once with their combined coverage
+        for y in ypxl1.min(ypxl2)..=ypxl1.max(ypxl2) + 1 {
+            let coverage: f32 = first
+                .iter()
+                .chain(second.iter())
+                .filter(|&&(row, _)| row == y)
+                .map(|&(_, coverage)| coverage)
+                .sum();
+            plot(xpxl1, y, coverage.min(1.0));
+        }
+        return;
+    }
+    for &(y, coverage) in first.iter() {
+        plot(xpxl1, y, coverage);
+    }
+    for &(y, coverage) in second.iter() {
+        plot(xpxl2, y, coverage);
+    }
+
+    // main loop, restricted to the visible part of the major axis
+    for x in (xpxl1 + 1).max(u_min)..=(xpxl2 - 1).min(u_max) {
+        let intery = y1 + gradient * (x as f32 - x1);
+        let y = intery.floor() as i32;
+        plot(x, y, rfpart(intery));
+        plot(x, y + 1, fpart(intery));
+    }
+}
+
+/// Calls `plot` with the pixels of one segment of a thin outline and their
+/// coverage, anti-aliased or not, which lie inside the inclusive `bounds`.
+pub(crate) fn segment_pixels<F: FnMut(i32, i32, f32)>(
+    from: (f32, f32),
+    to: (f32, f32),
+    antialias: bool,
+    bounds: (i32, i32, i32, i32),
+    mut plot: F,
+) {
+    if antialias {
+        wu_pixels(from.0, from.1, to.0, to.1, bounds, plot);
+    } else {
+        bresenham_pixels(
+            from.0.round() as i32,
+            from.1.round() as i32,
+            to.0.round() as i32,
+            to.1.round() as i32,
+            bounds,
+            |x, y| plot(x, y, 1.0),
+        );
+    }
+}
+
+/// Cohen-Sutherland outcode of a point relative to the inclusive bounds
+/// `(x_min, y_min, x_max, y_max)`.
+#[inline]
+pub(crate) fn outcode(x: i32, y: i32, bounds: (i32, i32, i32, i32)) -> u8 {
+    let mut code = 0;
+    if x < bounds.0 {
+        code |= 1;
+    } else if x > bounds.2 {
+        code |= 2;
+    }
+    if y < bounds.1 {
+        code |= 4;
+    } else if y > bounds.3 {
+        code |= 8;
+    }
+    code
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,85 +355,208 @@ mod tests {
 
     #[test]
     fn test_line_slope_less_than_one() {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        draw_line2d(0, 0, 4, 2, &mut canvas, &color::WHITE, &mut buffer[..]);
+        draw_line2d(0, 0, 4, 2, &mut canvas, &color::WHITE);
 
-        assert_eq!(canvas.get_color(0, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(1, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(2, 1, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(3, 1, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(4, 2, &mut buffer[..]), &WHITE);
+        assert_eq!(canvas.get_color(0, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(1, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(2, 1), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(3, 1), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(4, 2), Some(Color::from(WHITE)));
     }
 
     #[test]
     fn test_line_slope_one() {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        draw_line2d(0, 0, 4, 4, &mut canvas, &color::WHITE, &mut buffer[..]);
+        draw_line2d(0, 0, 4, 4, &mut canvas, &color::WHITE);
 
-        assert_eq!(canvas.get_color(0, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(1, 1, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(2, 2, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(3, 3, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(4, 4, &mut buffer[..]), &WHITE);
+        assert_eq!(canvas.get_color(0, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(1, 1), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(2, 2), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(3, 3), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(4, 4), Some(Color::from(WHITE)));
     }
 
     #[test]
     fn test_line_slope_greater_than_one() {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        draw_line2d(0, 0, 2, 4, &mut canvas, &color::WHITE, &mut buffer[..]);
+        draw_line2d(0, 0, 2, 4, &mut canvas, &color::WHITE);
 
-        assert_eq!(canvas.get_color(0, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(0, 1, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(1, 2, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(1, 3, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(2, 4, &mut buffer[..]), &WHITE);
+        assert_eq!(canvas.get_color(0, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(0, 1), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(1, 2), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(1, 3), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(2, 4), Some(Color::from(WHITE)));
     }
 
     #[test]
     fn test_line_slope_infinite() {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        draw_line2d(0, 0, 0, 4, &mut canvas, &color::WHITE, &mut buffer[..]);
+        draw_line2d(0, 0, 0, 4, &mut canvas, &color::WHITE);
 
-        assert_eq!(canvas.get_color(0, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(0, 1, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(0, 2, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(0, 3, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(0, 4, &mut buffer[..]), &WHITE);
+        assert_eq!(canvas.get_color(0, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(0, 1), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(0, 2), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(0, 3), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(0, 4), Some(Color::from(WHITE)));
     }
 
     #[test]
     fn test_line_slope_zero() {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        draw_line2d(0, 0, 4, 0, &mut canvas, &color::WHITE, &mut buffer[..]);
+        draw_line2d(0, 0, 4, 0, &mut canvas, &color::WHITE);
 
-        assert_eq!(canvas.get_color(0, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(1, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(2, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(3, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(4, 0, &mut buffer[..]), &WHITE);
+        assert_eq!(canvas.get_color(0, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(1, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(2, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(3, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(4, 0), Some(Color::from(WHITE)));
     }
 
     #[test]
     fn test_line_point_reorder() {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
-        draw_line2d(4, 2, 0, 0, &mut canvas, &color::WHITE, &mut buffer[..]);
+        draw_line2d(4, 2, 0, 0, &mut canvas, &color::WHITE);
+
+        assert_eq!(canvas.get_color(0, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(1, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(2, 1), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(3, 1), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(4, 2), Some(Color::from(WHITE)));
+    }
+
+    #[test]
+    fn test_line_clipped_matches_unclipped() {
+        const OFFSET: i32 = 100;
+        let lines = [
+            (-50, -20, 60, 35),
+            (5, -40, 7, 90),
+            (-30, 12, 40, -3),
+            (12, 3, -8, 8),
+            (-5, -5, -1, -1),
+        ];
+
+        for &(x1, y1, x2, y2) in lines.iter() {
+            let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+            draw_line2d(x1, y1, x2, y2, &mut canvas, &color::WHITE);
+
+            // draw the same line shifted inside a canvas big enough to hold it
+            let mut big_canvas = Canvas::new(300, 300).unwrap();
+            draw_line2d(
+                x1 + OFFSET,
+                y1 + OFFSET,
+                x2 + OFFSET,
+                y2 + OFFSET,
+                &mut big_canvas,
+                &color::WHITE,
+            );
+
+            for y in 0..HEIGHT as i32 {
+                for x in 0..WIDTH as i32 {
+                    assert_eq!(
+                        canvas.get_color(x, y),
+                        big_canvas.get_color(x + OFFSET, y + OFFSET),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_outside_canvas() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+
+        draw_line2d(-1, 0, -1, 9, &mut canvas, &color::WHITE);
+        draw_line2d(-100000, 11, 100000, 11, &mut canvas, &color::WHITE);
+
+        assert!(canvas.bytes().unwrap().iter().all(|&b| b == 0));
+        assert_eq!(canvas.get_color(-1, 0), None);
+        assert_eq!(canvas.get_color(0, 10), None);
+    }
+
+    #[test]
+    fn test_line_aa_coverage() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.clear(&color::BLACK);
+
+        draw_line2d_aa(0, 0, 4, 2, &mut canvas, &color::WHITE);
+
+        let half = [128, 128, 128, 255];
+        // line passes exactly between the two pixels
+        assert_eq!(canvas.get_color(1, 0), Some(Color::from(half)));
+        assert_eq!(canvas.get_color(1, 1), Some(Color::from(half)));
+        // and exactly through this one
+        assert_eq!(canvas.get_color(2, 1), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(2, 2), Some(Color::from([0, 0, 0, 255])));
+    }
+
+    #[test]
+    fn test_line_aa_single_pixel() {
+        let translucent = Color::new(255, 0, 0, 100);
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        draw_line2d_aa(5, 5, 5, 5, &mut canvas, &translucent);
+        assert_eq!(canvas.get_color(5, 5), Some(translucent));
+
+        // sub-pixel lines are blended once too
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_origin(0.25, 0.0);
+        draw_line2d_aa(5, 5, 5, 5, &mut canvas, &translucent);
+        assert_eq!(canvas.get_color(5, 5), Some(translucent));
+        assert_eq!(canvas.get_color(5, 4), Some(Color::new(0, 0, 0, 0)));
+        assert_eq!(canvas.get_color(5, 6), Some(Color::new(0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_line_shape_uses_canvas_antialias() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.clear(&color::BLACK);
+        canvas.set_antialias(true);
+
+        canvas.draw(&Line2D::new(0, 0, 4, 2), &color::WHITE);
+
+        let half = [128, 128, 128, 255];
+        assert_eq!(canvas.get_color(1, 0), Some(Color::from(half)));
+        assert_eq!(canvas.get_color(1, 1), Some(Color::from(half)));
+    }
+
+    #[test]
+    fn test_line_thick() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_line_width(3.0);
+
+        draw_line2d(2, 5, 8, 5, &mut canvas, &color::WHITE);
+
+        for y in 4..7 {
+            for x in 2..8 {
+                assert_eq!(canvas.get_color(x, y), Some(Color::from(WHITE)));
+            }
+        }
+        let empty = [0, 0, 0, 0];
+        assert_eq!(canvas.get_color(5, 3), Some(Color::from(empty)));
+        assert_eq!(canvas.get_color(5, 7), Some(Color::from(empty)));
+        assert_eq!(canvas.get_color(1, 5), Some(Color::from(empty)));
+        assert_eq!(canvas.get_color(8, 5), Some(Color::from(empty)));
+    }
+
+    #[test]
+    fn test_line_thick_diagonal() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_line_width(4.0);
+
+        canvas.draw(&Line2D::new(1, 1, 8, 8), &color::WHITE);
 
-        assert_eq!(canvas.get_color(0, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(1, 0, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(2, 1, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(3, 1, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(4, 2, &mut buffer[..]), &WHITE);
+        // pixels within two pixels of the diagonal are filled
+        assert_eq!(canvas.get_color(4, 4), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(5, 4), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(6, 4), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(3, 5), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(7, 4), Some(Color::from([0, 0, 0, 0])));
+        assert_eq!(canvas.get_color(2, 5), Some(Color::from([0, 0, 0, 0])));
     }
 }
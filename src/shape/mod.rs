@@ -3,24 +3,306 @@
 mod bezier2d;
 mod ellipse2d;
 mod line2d;
+mod path2d;
 mod polygon2d;
 mod rectangle2d;
+mod scanline;
+mod stroke;
 
 use crate::canvas::Canvas;
 use crate::Color;
 
 pub trait Shape {
-    fn draw(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]);
+    fn draw(&self, target: &mut dyn Target, color: &Color);
 
-    fn draw_filled(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]);
+    fn draw_filled(&self, target: &mut dyn Target, color: &Color);
 
     fn is_filled(&self) -> bool;
 }
 
+/// Receiver of the shapes being drawn, which turns them into pixels like
+/// [`Canvas`] or into another representation, like the SVG
+/// [`Writer`](crate::svg::Writer).
+///
+/// Shapes describe themselves through these primitives, given in shape
+/// coordinates. How outlines are stroked, like their width, is up to the
+/// target.
+pub trait Target {
+    /// Draws the line from `from` to `to`
+    fn line(&mut self, from: (i32, i32), to: (i32, i32), color: &Color);
+
+    /// Draws the rectangle at `(x, y)` of size `width x height`, filled or
+    /// as an outline
+    fn rect(&mut self, x: i32, y: i32, width: i32, height: i32, filled: bool, color: &Color);
+
+    /// Draws the axis aligned ellipse, filled or as an outline
+    fn ellipse(&mut self, center: (i32, i32), radii: (i32, i32), filled: bool, color: &Color);
+
+    /// Draws the outline connecting the points in order, the last one being
+    /// joined back to the first one if `closed` is set
+    fn polyline(&mut self, xi: &[i32], yi: &[i32], closed: bool, color: &Color);
+
+    /// Fills the polygon formed by the points in order
+    fn fill_polygon(&mut self, xi: &[i32], yi: &[i32], fill_rule: FillRule, color: &Color);
+
+    /// Draws the quadratic bezier curve
+    fn quadratic_bezier(
+        &mut self,
+        start: (i32, i32),
+        control: (i32, i32),
+        end: (i32, i32),
+        color: &Color,
+    );
+
+    /// Draws the cubic bezier curve
+    fn cubic_bezier(
+        &mut self,
+        start: (i32, i32),
+        control_a: (i32, i32),
+        control_b: (i32, i32),
+        end: (i32, i32),
+        color: &Color,
+    );
+
+    /// Draws the path, filled according to its fill rule or stroked
+    fn path(&mut self, path: &Path2D, filled: bool, color: &Color);
+}
+
+/// Rasterizes the shapes with the style of the canvas, anti-aliased if it is
+/// enabled on the canvas. Lines, ellipses and paths are first offered to the
+/// [`Backend`](crate::Backend) of the canvas, if it has one.
+impl Target for Canvas<'_> {
+    fn line(&mut self, from: (i32, i32), to: (i32, i32), color: &Color) {
+        let points = [
+            self.map_point_f32(from.0 as f32, from.1 as f32),
+            self.map_point_f32(to.0 as f32, to.1 as f32),
+        ];
+        if self.draw_with_backend(|backend, width| backend.line(points[0], points[1], width, color))
+        {
+            return;
+        }
+        stroke::stroke_polyline(&points, false, self, color);
+    }
+
+    fn rect(&mut self, x: i32, y: i32, width: i32, height: i32, filled: bool, color: &Color) {
+        if filled {
+            draw_rect2d_filled(x, y, width, height, self, color);
+        } else {
+            draw_rect2d(x, y, width, height, self, color);
+        }
+    }
+
+    fn ellipse(&mut self, center: (i32, i32), radii: (i32, i32), filled: bool, color: &Color) {
+        if self.is_axis_aligned() {
+            let center = self.map_point_f32(center.0 as f32, center.1 as f32);
+            let (width_radius, height_radius) = self.map_radii(radii.0, radii.1);
+            let radii = (width_radius as f32, height_radius as f32);
+            if self.draw_with_backend(|backend, width| {
+                backend.ellipse(center, radii, filled, width, color)
+            }) {
+                return;
+            }
+        }
+        let draw = match (filled, self.is_antialiased()) {
+            (false, false) => draw_ellipse2d,
+            (false, true) => draw_ellipse2d_aa,
+            (true, false) => draw_ellipse2d_filled,
+            (true, true) => draw_ellipse2d_filled_aa,
+        };
+        draw(center.0, center.1, radii.0, radii.1, self, color);
+    }
+
+    fn polyline(&mut self, xi: &[i32], yi: &[i32], closed: bool, color: &Color) {
+        if closed {
+            draw_polygon2d(xi, yi, self, color);
+        } else {
+            draw_polyline2d(xi, yi, self, color);
+        }
+    }
+
+    fn fill_polygon(&mut self, xi: &[i32], yi: &[i32], fill_rule: FillRule, color: &Color) {
+        draw_polygon2d_filled(xi, yi, fill_rule, self, color);
+    }
+
+    fn quadratic_bezier(
+        &mut self,
+        start: (i32, i32),
+        control: (i32, i32),
+        end: (i32, i32),
+        color: &Color,
+    ) {
+        draw_quadratic_bezier2d(start, end, control, self, color);
+    }
+
+    fn cubic_bezier(
+        &mut self,
+        start: (i32, i32),
+        control_a: (i32, i32),
+        control_b: (i32, i32),
+        end: (i32, i32),
+        color: &Color,
+    ) {
+        draw_cubic_bezier2d(start, end, control_a, control_b, self, color);
+    }
+
+    fn path(&mut self, path: &Path2D, filled: bool, color: &Color) {
+        if self.has_shape_backend() {
+            let pixels = path.map(|p| self.map_point_f32(p.0, p.1));
+            if self.draw_with_backend(|backend, width| backend.path(&pixels, filled, width, color))
+            {
+                return;
+            }
+        }
+        if filled {
+            path.fill(self, color);
+        } else {
+            path.stroke(self, color);
+        }
+    }
+}
+
 pub use bezier2d::{
     draw_cubic_bezier2d, draw_quadratic_bezier2d, CubicBezier2D, QuadraticBezier2D,
 };
-pub use ellipse2d::{draw_ellipse2d, draw_ellipse2d_filled, Ellipse2D};
-pub use line2d::{draw_line2d, Line2D};
-pub use polygon2d::{draw_polygon2d, draw_polygon2d_filled, Polygon2D};
+pub use ellipse2d::{
+    draw_ellipse2d, draw_ellipse2d_aa, draw_ellipse2d_filled, draw_ellipse2d_filled_aa, Ellipse2D,
+};
+pub use line2d::{draw_line2d, draw_line2d_aa, Line2D};
+pub use path2d::Path2D;
+pub(crate) use path2d::Segment;
+pub use polygon2d::{
+    draw_convex_hull2d, draw_polygon2d, draw_polygon2d_filled, draw_polyline2d, Polygon2D,
+    PolygonMode,
+};
 pub use rectangle2d::{draw_rect2d, draw_rect2d_filled, Rectangle2D};
+pub use scanline::FillRule;
+pub use stroke::{LineCap, LineJoin};
+
+pub(crate) use stroke::Stroke;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Backend;
+
+    const TRANSLUCENT: Color = Color {
+        r: 255,
+        g: 0,
+        b: 0,
+        a: 100,
+    };
+
+    /// Backend counting the writes to each pixel
+    struct Writes {
+        pixels: Vec<Color>,
+        counts: Vec<u32>,
+    }
+
+    impl Backend for Writes {
+        fn width(&self) -> usize {
+            64
+        }
+
+        fn height(&self) -> usize {
+            64
+        }
+
+        fn get_pixel(&self, x: usize, y: usize) -> Color {
+            self.pixels[x + y * 64]
+        }
+
+        fn set_pixel(&mut self, x: usize, y: usize, color: &Color) {
+            self.pixels[x + y * 64] = *color;
+            self.counts[x + y * 64] += 1;
+        }
+    }
+
+    /// Draws the shape in a translucent color, checking that no pixel is
+    /// written twice. Returns the number of pixels written.
+    fn count_writes<F: FnOnce(&mut Canvas)>(draw: F) -> usize {
+        let mut writes = Writes {
+            pixels: vec![Color::new(0, 0, 0, 0); 64 * 64],
+            counts: vec![0; 64 * 64],
+        };
+        draw(&mut Canvas::from_backend(&mut writes));
+
+        let twice: Vec<(usize, usize)> = (0..64 * 64)
+            .filter(|&i| writes.counts[i] > 1)
+            .map(|i| (i % 64, i / 64))
+            .collect();
+        assert!(twice.is_empty(), "pixels written twice: {:?}", twice);
+        writes.counts.iter().filter(|&&count| count == 1).count()
+    }
+
+    #[test]
+    fn test_outlines_write_pixels_once() {
+        let written = count_writes(|c| draw_rect2d(10, 10, 20, 12, c, &TRANSLUCENT));
+        assert_eq!(written, 2 * (20 + 12));
+        for radius in [0, 1, 7, 20].iter() {
+            count_writes(|c| draw_ellipse2d(32, 32, *radius, *radius, c, &TRANSLUCENT));
+        }
+        count_writes(|c| draw_ellipse2d(32, 32, 20, 9, c, &TRANSLUCENT));
+        count_writes(|c| draw_ellipse2d(32, 32, 1, 6, c, &TRANSLUCENT));
+
+        let (xi, yi) = ([5, 60, 30, 50, 8], [5, 10, 60, 5, 40]);
+        count_writes(|c| draw_polygon2d(&xi, &yi, c, &TRANSLUCENT));
+        count_writes(|c| draw_polyline2d(&xi, &yi, c, &TRANSLUCENT));
+        count_writes(|c| {
+            c.set_line_dash(&[5.0, 3.0]);
+            draw_rect2d(10, 10, 20, 12, c, &TRANSLUCENT)
+        });
+    }
+
+    #[test]
+    fn test_curves_write_pixels_once() {
+        count_writes(|c| draw_quadratic_bezier2d((2, 60), (60, 60), (30, -20), c, &TRANSLUCENT));
+        count_writes(|c| {
+            draw_cubic_bezier2d((2, 2), (60, 60), (80, 0), (-20, 60), c, &TRANSLUCENT)
+        });
+
+        let mut path = Path2D::new(false);
+        path.move_to(5.0, 5.0)
+            .line_to(50.0, 8.0)
+            .quad_to(60.0, 30.0, 40.0, 50.0)
+            .line_to(5.0, 5.0)
+            .move_to(20.0, 60.0)
+            .line_to(20.0, 0.0);
+        count_writes(|c| path.stroke(c, &TRANSLUCENT));
+        count_writes(|c| {
+            c.set_antialias(true);
+            path.stroke(c, &TRANSLUCENT)
+        });
+    }
+
+    #[test]
+    fn test_fills_write_pixels_once() {
+        let written = count_writes(|c| draw_rect2d_filled(10, 10, 20, 12, c, &TRANSLUCENT));
+        assert_eq!(written, 20 * 12);
+        for radius in [0, 1, 7, 20].iter() {
+            count_writes(|c| draw_ellipse2d_filled(32, 32, *radius, *radius, c, &TRANSLUCENT));
+        }
+        count_writes(|c| draw_ellipse2d_filled(32, 32, 20, 9, c, &TRANSLUCENT));
+        count_writes(|c| draw_ellipse2d_filled(32, 32, 1, 6, c, &TRANSLUCENT));
+        count_writes(|c| draw_ellipse2d_filled_aa(32, 32, 20, 9, c, &TRANSLUCENT));
+        count_writes(|c| {
+            c.set_line_width(5.0);
+            draw_ellipse2d(32, 32, 20, 9, c, &TRANSLUCENT)
+        });
+
+        let (xi, yi) = ([5, 60, 30, 50, 8], [5, 10, 60, 5, 40]);
+        count_writes(|c| draw_polygon2d_filled(&xi, &yi, FillRule::NonZero, c, &TRANSLUCENT));
+    }
+
+    #[test]
+    fn test_translucent_outline_has_no_seams() {
+        let mut canvas = Canvas::new(40, 40).unwrap();
+        draw_rect2d(5, 5, 20, 20, &mut canvas, &TRANSLUCENT);
+        draw_ellipse2d(20, 20, 10, 10, &mut canvas, &TRANSLUCENT);
+
+        // corners and the points where the octants meet are blended once
+        let once = Some(TRANSLUCENT);
+        for &(x, y) in [(5, 5), (25, 5), (25, 25), (5, 25), (20, 10), (30, 20)].iter() {
+            assert_eq!(canvas.get_color(x, y), once);
+        }
+    }
+}
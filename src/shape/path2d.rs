@@ -0,0 +1,432 @@
+use super::bezier2d::{filled_area, flatten_cubic, flatten_quadratic, stroked_area, Area};
+use super::scanline::{fill_contours, FillRule};
+use super::stroke::{stroke_subpaths, Point};
+use crate::canvas::Canvas;
+use crate::shape::{Shape, Target};
+use crate::Color;
+
+/// A piece of a [`Path2D`], with its points in shape coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Segment {
+    /// starts a new subpath at the point
+    MoveTo(Point),
+    /// straight line to the point
+    LineTo(Point),
+    /// quadratic curve through the control point to the end point
+    QuadTo(Point, Point),
+    /// cubic curve through the two control points to the end point
+    CubicTo(Point, Point, Point),
+    /// straight line back to the start of the subpath, closing it
+    Close,
+}
+
+/// An outline made of subpaths of lines and bezier curves, drawn as a whole.
+///
+/// Paths are built in the manner of the HTML canvas, each subpath starting
+/// with [`move_to`](Path2D::move_to) and continuing from the end of the
+/// previous segment. Stroking draws all subpaths with the stroke style of the
+/// canvas, pixels where they overlap being painted once, and filling fills
+/// them together according to the fill rule, so inner subpaths can cut
+/// holes.
+///
+/// ```ignore
+/// let mut path = Path2D::new(false);
+/// path.move_to(10.0, 10.0)
+///     .line_to(90.0, 10.0)
+///     .arc_to(110.0, 10.0, 110.0, 30.0, 20.0)
+///     .quad_to(110.0, 80.0, 60.0, 80.0)
+///     .close();
+/// canvas.draw(&path, &color::WHITE);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Path2D {
+    segments: Vec<Segment>,
+    /// first point of the current subpath
+    start: Option<Point>,
+    /// end point of the last segment
+    current: Option<Point>,
+    fill_rule: FillRule,
+    is_filled: bool,
+}
+
+impl Path2D {
+    pub fn new(fill: bool) -> Self {
+        Path2D {
+            is_filled: fill,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the rule deciding which regions of the path are inside when it
+    /// is filled. Defaults to [`FillRule::NonZero`].
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    /// Whether the path has no segments
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Rule deciding which regions of the path are inside when it is filled
+    pub fn fill_rule(&self) -> FillRule {
+        self.fill_rule
+    }
+
+    pub(crate) fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Copy of the path with each of its points passed through `map`
+    pub(crate) fn map<F: Fn(Point) -> Point>(&self, map: F) -> Path2D {
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| match *segment {
+                Segment::MoveTo(p) => Segment::MoveTo(map(p)),
+                Segment::LineTo(p) => Segment::LineTo(map(p)),
+                Segment::QuadTo(c, p) => Segment::QuadTo(map(c), map(p)),
+                Segment::CubicTo(c1, c2, p) => Segment::CubicTo(map(c1), map(c2), map(p)),
+                Segment::Close => Segment::Close,
+            })
+            .collect();
+        Path2D {
+            segments,
+            start: self.start.map(&map),
+            current: self.current.map(&map),
+            ..*self
+        }
+    }
+
+    /// End point of the last segment, `None` for an empty path
+    pub fn current_point(&self) -> Option<(f32, f32)> {
+        self.current
+    }
+
+    /// Starts a new subpath at `(x, y)`
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.segments.push(Segment::MoveTo((x, y)));
+        self.start = Some((x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Starts a subpath at `point` if there is none, like the HTML canvas
+    fn ensure_subpath(&mut self, point: Point) {
+        if self.current.is_none() {
+            self.move_to(point.0, point.1);
+        }
+    }
+
+    /// Adds a straight line to `(x, y)`
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.ensure_subpath((x, y));
+        self.segments.push(Segment::LineTo((x, y)));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a quadratic bezier curve through the control point `(cx, cy)`
+    /// to `(x, y)`
+    pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) -> &mut Self {
+        self.ensure_subpath((cx, cy));
+        self.segments.push(Segment::QuadTo((cx, cy), (x, y)));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a cubic bezier curve through the control points `(cx1, cy1)`
+    /// and `(cx2, cy2)` to `(x, y)`
+    pub fn cubic_to(
+        &mut self,
+        cx1: f32,
+        cy1: f32,
+        cx2: f32,
+        cy2: f32,
+        x: f32,
+        y: f32,
+    ) -> &mut Self {
+        self.ensure_subpath((cx1, cy1));
+        self.segments
+            .push(Segment::CubicTo((cx1, cy1), (cx2, cy2), (x, y)));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds an arc of the given radius rounding the corner at `(x1, y1)`
+    /// between the lines from the current point to `(x1, y1)` and from
+    /// `(x1, y1)` to `(x2, y2)`, joined to the current point by a straight
+    /// line. Same as `arcTo` of the HTML canvas.
+    ///
+    /// If the points are aligned or the radius is zero, a straight line to
+    /// `(x1, y1)` is added instead.
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) -> &mut Self {
+        self.ensure_subpath((x1, y1));
+        let p0 = self.current.unwrap_or((x1, y1));
+        let (p1, p2) = ((x1, y1), (x2, y2));
+
+        let (d0, d2) = ((p0.0 - p1.0, p0.1 - p1.1), (p2.0 - p1.0, p2.1 - p1.1));
+        let (l0, l2) = (d0.0.hypot(d0.1), d2.0.hypot(d2.1));
+        let cross = d0.0 * d2.1 - d0.1 * d2.0;
+        if radius <= 0.0 || !radius.is_finite() || l0 == 0.0 || l2 == 0.0 || cross == 0.0 {
+            return self.line_to(x1, y1);
+        }
+
+        // unit vectors from the corner along both lines, and the angle
+        // between them
+        let (u0, u2) = ((d0.0 / l0, d0.1 / l0), (d2.0 / l2, d2.1 / l2));
+        let angle = (u0.0 * u2.0 + u0.1 * u2.1).clamp(-1.0, 1.0).acos();
+        let tangent = radius / (angle / 2.0).tan();
+        let t0 = (p1.0 + u0.0 * tangent, p1.1 + u0.1 * tangent);
+        let t2 = (p1.0 + u2.0 * tangent, p1.1 + u2.1 * tangent);
+
+        // the center lies on the bisector of the corner
+        let (bx, by) = (u0.0 + u2.0, u0.1 + u2.1);
+        let bisector = bx.hypot(by);
+        let distance = radius / (angle / 2.0).sin();
+        let center = (
+            p1.0 + bx / bisector * distance,
+            p1.1 + by / bisector * distance,
+        );
+
+        let start = (t0.1 - center.1).atan2(t0.0 - center.0);
+        let end = (t2.1 - center.1).atan2(t2.0 - center.0);
+        let mut sweep = end - start;
+        if sweep > core::f32::consts::PI {
+            sweep -= core::f32::consts::TAU;
+        } else if sweep < -core::f32::consts::PI {
+            sweep += core::f32::consts::TAU;
+        }
+
+        self.line_to(t0.0, t0.1);
+        self.arc(center, (radius, radius), 0.0, start, sweep);
+        self
+    }
+
+    /// Adds the arc of the ellipse centered on `center` with the given radii
+    /// and rotated by `rotation` radians, from the angle `start` over
+    /// `sweep` radians, as cubic curves. The arc is expected to start at the
+    /// current point.
+    pub(crate) fn arc(
+        &mut self,
+        center: Point,
+        radii: Point,
+        rotation: f32,
+        start: f32,
+        sweep: f32,
+    ) {
+        // each cubic curve spans at most a quarter turn
+        let count = (sweep.abs() / core::f32::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+        let step = sweep / count as f32;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+
+        let (sin_r, cos_r) = rotation.sin_cos();
+        let place = |(x, y): Point| {
+            let (x, y) = (x * radii.0, y * radii.1);
+            (
+                center.0 + x * cos_r - y * sin_r,
+                center.1 + x * sin_r + y * cos_r,
+            )
+        };
+
+        for i in 0..count {
+            let (sin_a, cos_a) = (start + step * i as f32).sin_cos();
+            let (sin_b, cos_b) = (start + step * (i + 1) as f32).sin_cos();
+            let c1 = place((cos_a - k * sin_a, sin_a + k * cos_a));
+            let c2 = place((cos_b + k * sin_b, sin_b - k * cos_b));
+            let end = place((cos_b, sin_b));
+            self.cubic_to(c1.0, c1.1, c2.0, c2.1, end.0, end.1);
+        }
+    }
+
+    /// Closes the current subpath with a straight line back to its start.
+    /// Segments added next continue from that point.
+    pub fn close(&mut self) -> &mut Self {
+        if let Some(start) = self.start {
+            self.segments.push(Segment::Close);
+            self.current = Some(start);
+        }
+        self
+    }
+
+    /// Strokes all subpaths using the stroke style of the canvas,
+    /// anti-aliased if it is enabled on the canvas.
+    pub fn stroke(&self, canvas: &mut Canvas, color: &Color) {
+        let subpaths = self.flatten(canvas, stroked_area(canvas));
+        // a lone move has nothing to stroke
+        let subpaths: Vec<(&[Point], bool)> = subpaths
+            .iter()
+            .filter(|(points, _)| points.len() > 1)
+            .map(|(points, closed)| (&points[..], *closed))
+            .collect();
+        stroke_subpaths(&subpaths, canvas.is_antialiased(), canvas, color);
+    }
+
+    /// Fills the area enclosed by the subpaths, each one being closed
+    /// implicitly.
+    pub fn fill(&self, canvas: &mut Canvas, color: &Color) {
+        let subpaths = self.flatten(canvas, Some(filled_area(canvas)));
+        fill_contours(
+            subpaths.iter().map(|(points, _)| &points[..]),
+            self.fill_rule,
+            canvas,
+            color,
+        );
+    }
+
+    /// Maps the subpaths to pixels of the canvas and samples their curves
+    /// into polylines, flagging the closed ones. Parts of curves outside
+    /// `area` are not sampled.
+    fn flatten(&self, canvas: &Canvas, area: Option<Area>) -> Vec<(Vec<Point>, bool)> {
+        let mut subpaths = Vec::new();
+        let mut points: Vec<Point> = Vec::new();
+        let map = |p: Point| canvas.map_point_f32(p.0, p.1);
+
+        for segment in self.segments.iter() {
+            match *segment {
+                Segment::MoveTo(p) => {
+                    if !points.is_empty() {
+                        subpaths.push((core::mem::take(&mut points), false));
+                    }
+                    points.push(map(p));
+                }
+                Segment::LineTo(p) => points.push(map(p)),
+                Segment::QuadTo(c, p) => {
+                    let start = points.last().copied().unwrap_or_else(|| map(c));
+                    let curve = flatten_quadratic(start, map(c), map(p), area);
+                    points.extend_from_slice(&curve[1..]);
+                }
+                Segment::CubicTo(c1, c2, p) => {
+                    let start = points.last().copied().unwrap_or_else(|| map(c1));
+                    let curve = flatten_cubic(start, map(c1), map(c2), map(p), area);
+                    points.extend_from_slice(&curve[1..]);
+                }
+                Segment::Close => {
+                    if points.is_empty() {
+                        continue;
+                    }
+                    let start = points[0];
+                    // the closing line already gets back to the start
+                    if points.len() > 1 && points.last() == Some(&start) {
+                        points.pop();
+                    }
+                    subpaths.push((core::mem::take(&mut points), true));
+                    points.push(start);
+                }
+            }
+        }
+        // a trailing point left by close does not start a new subpath
+        if points.len() > 1 {
+            subpaths.push((points, false));
+        }
+
+        subpaths
+    }
+}
+
+impl Shape for Path2D {
+    fn draw(&self, target: &mut dyn Target, color: &Color) {
+        target.path(self, false, color);
+    }
+
+    fn draw_filled(&self, target: &mut dyn Target, color: &Color) {
+        target.path(self, true, color);
+    }
+
+    fn is_filled(&self) -> bool {
+        self.is_filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::shape::{draw_cubic_bezier2d, draw_line2d};
+
+    #[test]
+    fn test_path_matches_segments() {
+        let mut path = Path2D::new(false);
+        path.move_to(5.0, 5.0)
+            .line_to(30.0, 5.0)
+            .cubic_to(40.0, 5.0, 40.0, 30.0, 20.0, 35.0);
+        let mut canvas = Canvas::new(50, 50).unwrap();
+        canvas.draw(&path, &color::WHITE);
+
+        let mut expected = Canvas::new(50, 50).unwrap();
+        draw_line2d(5, 5, 30, 5, &mut expected, &color::WHITE);
+        draw_cubic_bezier2d(
+            (30, 5),
+            (20, 35),
+            (40, 5),
+            (40, 30),
+            &mut expected,
+            &color::WHITE,
+        );
+        assert_eq!(canvas.bytes(), expected.bytes());
+    }
+
+    #[test]
+    fn test_fill_with_hole() {
+        let empty = Some(Color::new(0, 0, 0, 0));
+        let mut path = Path2D::new(true).with_fill_rule(FillRule::EvenOdd);
+        path.move_to(0.0, 0.0)
+            .line_to(20.0, 0.0)
+            .line_to(20.0, 20.0)
+            .line_to(0.0, 20.0)
+            .close()
+            .move_to(5.0, 5.0)
+            .line_to(15.0, 5.0)
+            .line_to(15.0, 15.0)
+            .line_to(5.0, 15.0)
+            .close();
+        let mut canvas = Canvas::new(30, 30).unwrap();
+        canvas.draw(&path, &color::RED);
+
+        assert_eq!(canvas.get_color(2, 2), Some(color::RED));
+        assert_eq!(canvas.get_color(19, 10), Some(color::RED));
+        assert_eq!(canvas.get_color(10, 10), empty);
+        assert_eq!(canvas.get_color(20, 10), empty);
+    }
+
+    #[test]
+    fn test_translucent_stroke_paints_once() {
+        // thick strokes closing back onto the start and crossing subpaths
+        // still paint each pixel once
+        let half_red = Color::new(255, 0, 0, 128);
+        let mut path = Path2D::new(false);
+        path.move_to(2.0, 10.0)
+            .line_to(18.0, 10.0)
+            .line_to(10.0, 2.0)
+            .line_to(2.0, 10.0)
+            .close()
+            .move_to(10.0, 0.0)
+            .line_to(10.0, 19.0);
+
+        let mut canvas = Canvas::new(20, 20).unwrap();
+        canvas.set_line_width(3.0);
+        canvas.draw(&path, &half_red);
+        assert_eq!(canvas.get_color(2, 10), Some(half_red));
+        assert_eq!(canvas.get_color(10, 10), Some(half_red));
+        assert_eq!(canvas.get_color(10, 18), Some(half_red));
+    }
+
+    #[test]
+    fn test_arc_to() {
+        // rounded corner of radius 10 at (20, 0)
+        let mut path = Path2D::new(false);
+        path.move_to(0.0, 0.0).arc_to(20.0, 0.0, 20.0, 20.0, 10.0);
+        let end = path.current_point().unwrap();
+        assert!((end.0 - 20.0).abs() < 1e-4 && (end.1 - 10.0).abs() < 1e-4);
+
+        let mut canvas = Canvas::new(30, 30).unwrap();
+        canvas.draw(&path, &color::WHITE);
+        let empty = Some(Color::new(0, 0, 0, 0));
+        assert_eq!(canvas.get_color(5, 0), Some(color::WHITE));
+        assert_eq!(canvas.get_color(20, 10), Some(color::WHITE));
+        // 45 degrees along the arc, and the skipped corner
+        assert_eq!(canvas.get_color(17, 3), Some(color::WHITE));
+        assert_eq!(canvas.get_color(20, 0), empty);
+    }
+}
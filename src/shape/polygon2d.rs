@@ -1,31 +1,85 @@
-use super::line2d::draw_line2d;
+use super::scanline::{fill_contours, FillRule};
+use super::stroke::stroke_polyline;
 use crate::canvas::Canvas;
-use crate::shape::Shape;
+use crate::shape::{Shape, Target};
 use crate::Color;
 use std::cmp::Ordering;
 
+/// Decides how the vertices of a [`Polygon2D`] are connected
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum PolygonMode {
+    /// Vertices are connected in the given order and the last vertex is
+    /// joined back to the first one.
+    #[default]
+    Closed,
+    /// Vertices are connected in the given order, leaving the path open.
+    Open,
+    /// Only the convex hull of the vertices is drawn.
+    ConvexHull,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Polygon2D<'a> {
     xi: &'a [i32],
     yi: &'a [i32],
+    is_filled: bool,
+    fill_rule: FillRule,
+    mode: PolygonMode,
 }
 
 impl<'a> Polygon2D<'a> {
-    pub fn new(xi: &'a [i32], yi: &'a [i32]) -> Self {
-        return Polygon2D { xi, yi };
+    pub fn new(xi: &'a [i32], yi: &'a [i32], fill: bool) -> Self {
+        Polygon2D {
+            xi,
+            yi,
+            is_filled: fill,
+            fill_rule: FillRule::default(),
+            mode: PolygonMode::default(),
+        }
+    }
+
+    /// Sets the rule used to decide the inside of the polygon when filled
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    /// Sets how the vertices are connected when drawing the outline
+    pub fn with_mode(mut self, mode: PolygonMode) -> Self {
+        self.mode = mode;
+        self
     }
 }
 
 impl<'a> Shape for Polygon2D<'a> {
-    fn draw(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_polygon2d(self.xi, self.yi, canvas, color, buffer);
+    fn draw(&self, target: &mut dyn Target, color: &Color) {
+        if self.xi.len() != self.yi.len() {
+            return;
+        }
+        match self.mode {
+            PolygonMode::Closed => target.polyline(self.xi, self.yi, true, color),
+            PolygonMode::Open => target.polyline(self.xi, self.yi, false, color),
+            PolygonMode::ConvexHull => {
+                let (xi, yi) = convex_hull_points(self.xi, self.yi);
+                target.polyline(&xi, &yi, true, color);
+            }
+        }
     }
 
-    fn draw_filled(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_polygon2d_filled(self.xi, self.yi, canvas, color, buffer);
+    fn draw_filled(&self, target: &mut dyn Target, color: &Color) {
+        if self.xi.len() != self.yi.len() {
+            return;
+        }
+        if self.mode == PolygonMode::ConvexHull {
+            let (xi, yi) = convex_hull_points(self.xi, self.yi);
+            target.fill_polygon(&xi, &yi, self.fill_rule, color);
+        } else {
+            target.fill_polygon(self.xi, self.yi, self.fill_rule, color);
+        }
     }
+
     fn is_filled(&self) -> bool {
-        false
+        self.is_filled
     }
 }
 
@@ -48,7 +102,11 @@ fn orientation(px: i32, py: i32, qx: i32, qy: i32, rx: i32, ry: i32) -> i32 {
         return 0;
     }
     // clock or counter-clock wise
-    return if val > 0 { 1 } else { 2 };
+    if val > 0 {
+        1
+    } else {
+        2
+    }
 }
 
 fn create_convex_hull(xi: &[i32], yi: &[i32]) -> Vec<usize> {
@@ -56,6 +114,9 @@ fn create_convex_hull(xi: &[i32], yi: &[i32]) -> Vec<usize> {
     let mut ipoints = vec![0usize; num_points];
     let mut start_index = 0;
     let mut hull: Vec<usize> = Vec::new();
+    if num_points < 3 {
+        return hull;
+    }
 
     // Step 1: Find the bottom most point, i.e point with least y coordinate
     for i in 0..num_points {
@@ -88,11 +149,11 @@ fn create_convex_hull(xi: &[i32], yi: &[i32]) -> Vec<usize> {
                 Ordering::Greater
             };
         }
-        return if o == 2 {
+        if o == 2 {
             Ordering::Less
         } else {
             Ordering::Greater
-        };
+        }
     });
 
     // Step 3: Remove two or more points which make same angle with initial index
@@ -110,11 +171,11 @@ fn create_convex_hull(xi: &[i32], yi: &[i32]) -> Vec<usize> {
                 yi[ipoints[k + 1]],
             ) == 0
         {
-            k = k + 1;
+            k += 1;
         }
         ipoints[m] = ipoints[k];
-        m = m + 1;
-        k = k + 1;
+        m += 1;
+        k += 1;
     }
     if m < 4 {
         return hull;
@@ -144,57 +205,81 @@ fn create_convex_hull(xi: &[i32], yi: &[i32]) -> Vec<usize> {
         hull.push(ipoints[i]);
     }
 
-    return hull;
+    hull
 }
 
-/// Renders a convex polygon formed by the given points. It calculates the convex hull using [Graham Scan Algorithm](https://en.wikipedia.org/wiki/Graham_scan)
+/// Renders the polygon formed by connecting the given points in order, and
+/// the last point back to the first one. The edges are drawn using the line
+/// width of the canvas, anti-aliased if it is enabled on the canvas.
+///
+/// The points can describe any shape, including concave and self
+/// intersecting ones. The provided list of points should be an open path,
+/// i.e first and last should not be same
+pub fn draw_polygon2d(xi: &[i32], yi: &[i32], canvas: &mut Canvas, color: &Color) {
+    if xi.len() != yi.len() {
+        return;
+    }
+
+    stroke_polyline(&to_points(xi, yi, canvas), true, canvas, color);
+}
+
+/// Renders the open path formed by connecting the given points in order.
+pub fn draw_polyline2d(xi: &[i32], yi: &[i32], canvas: &mut Canvas, color: &Color) {
+    if xi.len() != yi.len() {
+        return;
+    }
+
+    stroke_polyline(&to_points(xi, yi, canvas), false, canvas, color);
+}
+
+/// Pairs up the coordinates and maps them to pixels of the canvas
+fn to_points(xi: &[i32], yi: &[i32], canvas: &Canvas) -> Vec<(f32, f32)> {
+    xi.iter()
+        .zip(yi.iter())
+        .map(|(&x, &y)| canvas.map_point_f32(x as f32, y as f32))
+        .collect()
+}
+
+/// Renders the convex hull of the given points. It calculates the convex hull using [Graham Scan Algorithm](https://en.wikipedia.org/wiki/Graham_scan)
 ///
 /// The provided list of points should be an open path, i.e first and last
 /// should not be same
-pub fn draw_polygon2d(
-    xi: &[i32],
-    yi: &[i32],
-    canvas: &mut Canvas,
-    color: &Color,
-    buffer: &mut [u8],
-) {
+pub fn draw_convex_hull2d(xi: &[i32], yi: &[i32], canvas: &mut Canvas, color: &Color) {
     if xi.len() != yi.len() {
         return;
     }
 
-    let hull = create_convex_hull(xi, yi);
+    let (hxi, hyi) = convex_hull_points(xi, yi);
+    draw_polygon2d(&hxi, &hyi, canvas, color);
+}
 
-    let hull_len = hull.len() - 1;
-    for i in 0..hull_len {
-        draw_line2d(
-            xi[hull[i]],
-            yi[hull[i]],
-            xi[hull[i + 1]],
-            yi[hull[i + 1]],
-            canvas,
-            color,
-            buffer,
-        );
-    }
-    // draw closing line from last index to first index
-    draw_line2d(
-        xi[hull[hull_len]],
-        yi[hull[hull_len]],
-        xi[hull[0]],
-        yi[hull[0]],
-        canvas,
-        color,
-        buffer,
-    );
+/// Coordinates of the points on the convex hull, in order
+fn convex_hull_points(xi: &[i32], yi: &[i32]) -> (Vec<i32>, Vec<i32>) {
+    let hull = create_convex_hull(xi, yi);
+    (
+        hull.iter().map(|&i| xi[i]).collect(),
+        hull.iter().map(|&i| yi[i]).collect(),
+    )
 }
 
+/// Fills the polygon formed by the given points in order, using scanline
+/// filling with an active edge table.
+///
+/// Works for any simple or self intersecting polygon, `fill_rule` decides
+/// which of the enclosed regions are inside. The path is closed implicitly.
 pub fn draw_polygon2d_filled(
-    _xi: &[i32],
-    _yi: &[i32],
-    _canvas: &mut Canvas,
-    _color: &Color,
-    _buffer: &mut [u8],
+    xi: &[i32],
+    yi: &[i32],
+    fill_rule: FillRule,
+    canvas: &mut Canvas,
+    color: &Color,
 ) {
+    if xi.len() != yi.len() {
+        return;
+    }
+
+    let points = to_points(xi, yi, canvas);
+    fill_contours(Some(&points[..]), fill_rule, canvas, color);
 }
 
 #[cfg(test)]
@@ -208,20 +293,93 @@ mod tests {
 
     #[test]
     fn test_polygon_correct_convex_hull() {
-        let mut buffer = vec![0u8; 4 * WIDTH * HEIGHT];
         let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
 
         let xi: [i32; 10] = [127, 243, 62, 110, 93, 193, 135, 70, 258, 248];
         let yi: [i32; 10] = [320, 15, 162, 54, 311, 314, 290, 10, 163, 155];
 
-        draw_polygon2d(&xi, &yi, &mut canvas, &color::WHITE, &mut buffer[..]);
+        draw_convex_hull2d(&xi, &yi, &mut canvas, &color::WHITE);
+
+        assert_eq!(canvas.get_color(70, 10), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(243, 15), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(258, 163), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(193, 314), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(127, 320), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(93, 311), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(62, 162), Some(Color::from(WHITE)));
+    }
+
+    #[test]
+    fn test_polygon_draws_path_in_order() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+
+        // arrow head, (20, 10) lies inside the convex hull
+        let xi: [i32; 4] = [0, 40, 0, 20];
+        let yi: [i32; 4] = [0, 10, 20, 10];
+
+        draw_polygon2d(&xi, &yi, &mut canvas, &color::WHITE);
+
+        assert_eq!(canvas.get_color(20, 10), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(10, 5), Some(Color::from(WHITE)));
+        // closing edge of the hull is not part of the path
+        assert_eq!(canvas.get_color(0, 10), Some(Color::from([0, 0, 0, 0])));
+    }
+
+    #[test]
+    fn test_polyline_is_open() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+
+        let xi: [i32; 3] = [0, 10, 10];
+        let yi: [i32; 3] = [0, 0, 10];
+
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+
+        assert_eq!(canvas.get_color(5, 0), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(10, 5), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(5, 5), Some(Color::from([0, 0, 0, 0])));
+    }
+
+    #[test]
+    fn test_polygon_filled_concave() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+
+        // L shape
+        let xi: [i32; 6] = [10, 30, 30, 20, 20, 10];
+        let yi: [i32; 6] = [10, 10, 20, 20, 40, 40];
+
+        draw_polygon2d_filled(&xi, &yi, FillRule::NonZero, &mut canvas, &color::WHITE);
+
+        assert_eq!(canvas.get_color(10, 10), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(29, 19), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(15, 39), Some(Color::from(WHITE)));
+        // notch of the L, right and bottom edges are exclusive
+        assert_eq!(canvas.get_color(25, 25), Some(Color::from([0, 0, 0, 0])));
+        assert_eq!(canvas.get_color(30, 15), Some(Color::from([0, 0, 0, 0])));
+        assert_eq!(canvas.get_color(15, 40), Some(Color::from([0, 0, 0, 0])));
+    }
+
+    #[test]
+    fn test_polygon_filled_fill_rule() {
+        // pentagram, center is enclosed twice
+        let xi: [i32; 5] = [100, 159, 5, 195, 41];
+        let yi: [i32; 5] = [10, 190, 69, 69, 190];
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        draw_polygon2d_filled(&xi, &yi, FillRule::EvenOdd, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(100, 110), Some(Color::from([0, 0, 0, 0])));
+        assert_eq!(canvas.get_color(100, 40), Some(Color::from(WHITE)));
+
+        canvas.clear(&Color::new(0, 0, 0, 0));
+        draw_polygon2d_filled(&xi, &yi, FillRule::NonZero, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(100, 110), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(100, 40), Some(Color::from(WHITE)));
+    }
+
+    #[test]
+    fn test_polygon_is_filled() {
+        let xi: [i32; 3] = [0, 10, 0];
+        let yi: [i32; 3] = [0, 0, 10];
 
-        assert_eq!(canvas.get_color(70, 10, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(243, 15, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(258, 163, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(193, 314, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(127, 320, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(93, 311, &mut buffer[..]), &WHITE);
-        assert_eq!(canvas.get_color(62, 162, &mut buffer[..]), &WHITE);
+        assert!(!Polygon2D::new(&xi, &yi, false).is_filled());
+        assert!(Polygon2D::new(&xi, &yi, true).is_filled());
     }
 }
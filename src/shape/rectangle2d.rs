@@ -1,6 +1,7 @@
-use super::line2d::draw_line2d;
+use super::scanline::{fill_contours, FillRule};
+use super::stroke::stroke_polyline;
 use crate::canvas::Canvas;
-use crate::shape::Shape;
+use crate::shape::{Shape, Target};
 use crate::Color;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -25,49 +26,28 @@ impl Rectangle2D {
 }
 
 impl Shape for Rectangle2D {
-    fn draw(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_rect2d(
-            self.x,
-            self.y,
-            self.width,
-            self.height,
-            canvas,
-            color,
-            buffer,
-        );
+    fn draw(&self, target: &mut dyn Target, color: &Color) {
+        target.rect(self.x, self.y, self.width, self.height, false, color);
     }
 
-    fn draw_filled(&self, canvas: &mut Canvas, color: &Color, buffer: &mut [u8]) {
-        draw_rect2d_filled(
-            self.x,
-            self.y,
-            self.width,
-            self.height,
-            canvas,
-            color,
-            buffer,
-        );
+    fn draw_filled(&self, target: &mut dyn Target, color: &Color) {
+        target.rect(self.x, self.y, self.width, self.height, true, color);
     }
+
     fn is_filled(&self) -> bool {
         self.is_filled
     }
 }
 
-pub fn draw_rect2d(
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
-    canvas: &mut Canvas,
-    color: &Color,
-    buffer: &mut [u8],
-) {
-    draw_line2d(x, y, x + width, y, canvas, color, buffer);
-    draw_line2d(x, y, x, y + height, canvas, color, buffer);
-    draw_line2d(x + width, y, x + width, y + height, canvas, color, buffer);
-    draw_line2d(x, y + height, x + width, y + height, canvas, color, buffer);
+/// Draws the outline of the rectangle using the line width of the canvas,
+/// anti-aliased if it is enabled on the canvas.
+pub fn draw_rect2d(x: i32, y: i32, width: i32, height: i32, canvas: &mut Canvas, color: &Color) {
+    let corners = corners(x, y, width, height, canvas);
+    stroke_polyline(&corners, true, canvas, color);
 }
 
+/// Fills the pixels whose centers lie in the rectangle, its edges at `x` and
+/// `y` being inclusive and the ones at `x + width` and `y + height` exclusive.
 pub fn draw_rect2d_filled(
     x: i32,
     y: i32,
@@ -75,11 +55,64 @@ pub fn draw_rect2d_filled(
     height: i32,
     canvas: &mut Canvas,
     color: &Color,
-    buffer: &mut [u8],
 ) {
-    for i in x..(x + width) {
-        for j in y..(y + height) {
-            canvas.draw_point(i, j, color, buffer);
-        }
+    // a rotated or skewed rectangle is filled as a polygon
+    if !canvas.is_axis_aligned() {
+        let corners = corners(x, y, width, height, canvas);
+        fill_contours(Some(&corners[..]), FillRule::NonZero, canvas, color);
+        return;
+    }
+
+    // corners in pixels, which get swapped by a y axis pointing up
+    let (x1, y1) = canvas.map_point_f64(x as f64, y as f64);
+    let (x2, y2) = canvas.map_point_f64(x as f64 + width as f64, y as f64 + height as f64);
+    let (x1, x2) = pixel_range(x1, x2);
+    let (y1, y2) = pixel_range(y1, y2);
+
+    // clip the rectangle to the canvas
+    let (x_min, y_min, x_max, y_max) = canvas.clip_bounds();
+    let x_start = x1.max(x_min as i64);
+    let x_end = x2.min(x_max as i64 + 1);
+    let y_start = y1.max(y_min as i64);
+    let y_end = y2.min(y_max as i64 + 1);
+
+    if x_start >= x_end {
+        return;
+    }
+    for j in y_start..y_end {
+        canvas.draw_span(x_start as i32, x_end as i32, j as i32, color);
+    }
+}
+
+/// Corners of the rectangle in order, mapped to pixels of the canvas
+fn corners(x: i32, y: i32, width: i32, height: i32, canvas: &Canvas) -> [(f32, f32); 4] {
+    let (x1, y1) = (x as f32, y as f32);
+    let (x2, y2) = (x as f32 + width as f32, y as f32 + height as f32);
+    [(x1, y1), (x2, y1), (x2, y2), (x1, y2)].map(|(x, y)| canvas.map_point_f32(x, y))
+}
+
+/// Range of the pixels whose centers lie between the mapped edges `start`
+/// and `end`, keeping the edge at `start` inclusive even when the axis is
+/// flipped.
+#[inline]
+fn pixel_range(start: f64, end: f64) -> (i64, i64) {
+    if start <= end {
+        (start.ceil() as i64, end.ceil() as i64)
+    } else {
+        (end.floor() as i64 + 1, start.floor() as i64 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use test::Bencher;
+
+    #[bench]
+    fn bench_render_rect_filled(b: &mut Bencher) {
+        let mut canvas = Canvas::new(400, 400).unwrap();
+
+        b.iter(|| draw_rect2d_filled(0, 0, 400, 400, &mut canvas, &color::WHITE));
     }
 }
@@ -0,0 +1,150 @@
+//! Scanline polygon filling with an active edge table.
+//!
+//! Vertices use the same convention as the rest of the shape module, integer
+//! coordinates sit at pixel centers. A pixel is filled when its center lies
+//! inside the polygon, with left and top edges inclusive and right and bottom
+//! edges exclusive, so adjacent polygons sharing an edge never overlap.
+
+use crate::canvas::Canvas;
+use crate::Color;
+
+/// Rule used to decide which regions of a self intersecting or nested
+/// polygon are inside.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum FillRule {
+    /// A point is inside if a ray from it crosses the outline an odd number
+    /// of times.
+    EvenOdd,
+    /// A point is inside if the outline winds around it a non-zero number of
+    /// times.
+    #[default]
+    NonZero,
+}
+
+/// A non horizontal polygon edge, stored top to bottom.
+#[derive(Debug, Copy, Clone)]
+struct Edge {
+    /// top end point of the edge
+    x0: f32,
+    y0: f32,
+    /// change in x per unit change in y
+    slope: f32,
+    /// first scanline crossed by the edge
+    y_start: i32,
+    /// scanline after the last one crossed by the edge
+    y_end: i32,
+    /// +1 for edges going down, -1 for edges going up
+    winding: i32,
+}
+
+impl Edge {
+    fn new(from: (f32, f32), to: (f32, f32)) -> Option<Edge> {
+        let (top, bottom, winding) = if from.1 < to.1 {
+            (from, to, 1)
+        } else {
+            (to, from, -1)
+        };
+        let y_start = top.1.ceil() as i32;
+        let y_end = bottom.1.ceil() as i32;
+        // horizontal edges, or edges not crossing any scanline center
+        if y_start >= y_end {
+            return None;
+        }
+
+        Some(Edge {
+            x0: top.0,
+            y0: top.1,
+            slope: (bottom.0 - top.0) / (bottom.1 - top.1),
+            y_start,
+            y_end,
+            winding,
+        })
+    }
+
+    #[inline]
+    fn x_at(&self, y: i32) -> f32 {
+        self.x0 + (y as f32 - self.y0) * self.slope
+    }
+}
+
+/// Builds the edge table for the given closed contours, sorted by first
+/// scanline.
+fn build_edge_table<'p, I>(contours: I) -> Vec<Edge>
+where
+    I: IntoIterator<Item = &'p [(f32, f32)]>,
+{
+    let mut edges = Vec::new();
+    for points in contours {
+        let n = points.len();
+        if n < 2 {
+            continue;
+        }
+        for i in 0..n {
+            if let Some(edge) = Edge::new(points[i], points[(i + 1) % n]) {
+                edges.push(edge);
+            }
+        }
+    }
+    edges.sort_by_key(|e| e.y_start);
+
+    edges
+}
+
+/// Fills the area enclosed by the closed contours according to `rule`.
+///
+/// All contours are filled together, so holes can be made either by
+/// reversing the direction of inner contours or by using the even-odd rule.
+pub(crate) fn fill_contours<'p, I>(contours: I, rule: FillRule, canvas: &mut Canvas, color: &Color)
+where
+    I: IntoIterator<Item = &'p [(f32, f32)]>,
+{
+    let edges = build_edge_table(contours);
+    if edges.is_empty() {
+        return;
+    }
+
+    // clip the scanlines and spans to the canvas
+    let (_, clip_y_min, _, clip_y_max) = canvas.clip_bounds();
+    let y_min = edges[0].y_start.max(clip_y_min);
+    let y_max = edges
+        .iter()
+        .map(|e| e.y_end)
+        .max()
+        .unwrap_or(y_min)
+        .min(clip_y_max.saturating_add(1));
+
+    let mut next_edge = 0;
+    let mut active: Vec<Edge> = Vec::new();
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+
+    for y in y_min..y_max {
+        // move edges starting at this scanline into the active edge table and
+        // retire the ones which have ended
+        while next_edge < edges.len() && edges[next_edge].y_start <= y {
+            active.push(edges[next_edge]);
+            next_edge += 1;
+        }
+        active.retain(|e| e.y_end > y);
+
+        crossings.clear();
+        crossings.extend(active.iter().map(|e| (e.x_at(y), e.winding)));
+        crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal));
+
+        let mut winding = 0;
+        for i in 0..crossings.len().saturating_sub(1) {
+            winding += match rule {
+                FillRule::EvenOdd => 1,
+                FillRule::NonZero => crossings[i].1,
+            };
+            let inside = match rule {
+                FillRule::EvenOdd => winding % 2 != 0,
+                FillRule::NonZero => winding != 0,
+            };
+            if inside {
+                let x_start = crossings[i].0.ceil() as i32;
+                let x_end = crossings[i + 1].0.ceil() as i32;
+                canvas.draw_span(x_start, x_end, y, color);
+            }
+        }
+    }
+}
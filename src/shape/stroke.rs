@@ -0,0 +1,620 @@
+//! Stroking of outlines wider than a pixel.
+//!
+//! Thin outlines are rasterized segment by segment with the line algorithms,
+//! the pixels shared by segments being painted once.
+//! Wider ones are turned into polygons, a quad per segment plus the caps and
+//! joins, which are filled together in a single pass so overlapping parts of
+//! the stroke are only painted once.
+//!
+//! Dashed outlines are first split into one open path per dash, measuring
+//! the pattern along the whole outline so it flows across its corners.
+
+use super::line2d::segment_pixels;
+use super::scanline::{fill_contours, FillRule};
+use crate::canvas::Canvas;
+use crate::Color;
+
+pub(crate) type Point = (f32, f32);
+
+/// Shape used at the ends of open outlines
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum LineCap {
+    /// Outline ends exactly at the end point
+    #[default]
+    Butt,
+    /// Outline ends with a half circle around the end point
+    Round,
+    /// Outline is extended past the end point by half the line width
+    Square,
+}
+
+/// Shape used where two segments of an outline meet
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Outer edges are extended until they meet, falling back to a bevel
+    /// when the tip gets longer than the miter limit
+    #[default]
+    Miter,
+    /// Corner is rounded with a circle around the joint
+    Round,
+    /// Outer corners of the segments are connected by a straight edge
+    Bevel,
+}
+
+/// Style used when stroking outlines
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Stroke {
+    /// width of the stroke in pixels
+    pub(crate) width: f32,
+    /// shape of the ends of open outlines
+    pub(crate) cap: LineCap,
+    /// shape of the corners
+    pub(crate) join: LineJoin,
+    /// maximum ratio of miter length to line width
+    pub(crate) miter_limit: f32,
+    /// alternating lengths of dashes and gaps, empty for solid outlines
+    pub(crate) dash: Vec<f32>,
+    /// distance into the dash pattern at which outlines start
+    pub(crate) dash_offset: f32,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Stroke {
+            width: 1.0,
+            cap: LineCap::default(),
+            join: LineJoin::default(),
+            miter_limit: 10.0,
+            dash: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl Stroke {
+    /// Whether the stroke is drawn with the one pixel wide line algorithms
+    #[inline]
+    pub(crate) fn is_thin(&self) -> bool {
+        self.width <= 1.0
+    }
+
+    /// Whether outlines are drawn with a dash pattern
+    #[inline]
+    pub(crate) fn is_dashed(&self) -> bool {
+        !self.dash.is_empty()
+    }
+}
+
+/// Strokes the path through `points` using the stroke style of the canvas.
+/// If `closed` is set the last point is joined back to the first one.
+pub(crate) fn stroke_polyline(points: &[Point], closed: bool, canvas: &mut Canvas, color: &Color) {
+    let antialias = canvas.is_antialiased();
+    stroke_path(points, closed, antialias, canvas, color);
+}
+
+/// Same as [`stroke_polyline`], but thin outlines are anti-aliased depending
+/// on `antialias` instead of the canvas setting.
+pub(crate) fn stroke_path(
+    points: &[Point],
+    closed: bool,
+    antialias: bool,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    stroke_subpaths(&[(points, closed)], antialias, canvas, color);
+}
+
+/// Strokes the paths together, each one being closed if its flag is set, so
+/// pixels where they overlap are painted once.
+pub(crate) fn stroke_subpaths(
+    subpaths: &[(&[Point], bool)],
+    antialias: bool,
+    canvas: &mut Canvas,
+    color: &Color,
+) {
+    let stroke = canvas.stroke().clone();
+
+    let dashes: Vec<Vec<Point>>;
+    let paths: Vec<(&[Point], bool)> = if stroke.is_dashed() {
+        dashes = subpaths
+            .iter()
+            .flat_map(|&(points, closed)| {
+                dash_polyline(points, closed, &stroke.dash, stroke.dash_offset)
+            })
+            .collect();
+        dashes.iter().map(|dash| (&dash[..], false)).collect()
+    } else {
+        subpaths.to_vec()
+    };
+
+    if stroke.is_thin() {
+        let bounds = canvas.clip_bounds();
+        let mut pixels = Vec::new();
+        let mut plot = |x, y, coverage| pixels.push((y, x, coverage));
+        for &(path, closed) in paths.iter() {
+            for (from, to) in segments(path, closed) {
+                segment_pixels(from, to, antialias, bounds, &mut plot);
+            }
+            // a zero length dash still shows up as a dot
+            if path.len() == 1 {
+                segment_pixels(path[0], path[0], antialias, bounds, &mut plot);
+            }
+        }
+        plot_once(pixels, canvas, color);
+        return;
+    }
+
+    let polygons: Vec<Vec<Point>> = paths
+        .iter()
+        .flat_map(|&(path, closed)| stroke_polygons(path, closed, &stroke))
+        .collect();
+    fill_contours(
+        polygons.iter().map(|p| &p[..]),
+        FillRule::NonZero,
+        canvas,
+        color,
+    );
+}
+
+/// Plots the `(y, x, coverage)` pixels of the segments of thin outlines,
+/// those shared by several segments, like their joints, only once with the
+/// largest of their coverages. Runs of fully covered pixels are drawn as
+/// spans.
+fn plot_once(mut pixels: Vec<(i32, i32, f32)>, canvas: &mut Canvas, color: &Color) {
+    pixels.sort_unstable_by_key(|&(y, x, _)| (y, x));
+    pixels.dedup_by(|next, kept| {
+        let same = (next.0, next.1) == (kept.0, kept.1);
+        if same {
+            kept.2 = kept.2.max(next.2);
+        }
+        same
+    });
+
+    let mut i = 0;
+    while i < pixels.len() {
+        let (y, x, coverage) = pixels[i];
+        if coverage < 1.0 {
+            canvas.blend_point(x, y, color, coverage);
+            i += 1;
+            continue;
+        }
+        let mut end = i + 1;
+        while end < pixels.len()
+            && pixels[end].0 == y
+            && pixels[end].1 == x + (end - i) as i32
+            && pixels[end].2 >= 1.0
+        {
+            end += 1;
+        }
+        canvas.draw_span(x, x + (end - i) as i32, y, color);
+        i = end;
+    }
+}
+
+/// Splits the path into the open paths of its dashes.
+///
+/// `pattern` alternates the lengths of dashes and gaps, starting with a dash,
+/// and `offset` is how far into the pattern the path starts. The pattern is
+/// measured along the whole path, continuing from one segment to the next.
+fn dash_polyline(points: &[Point], closed: bool, pattern: &[f32], offset: f32) -> Vec<Vec<Point>> {
+    let total: f32 = pattern.iter().sum();
+    let mut dashes = Vec::new();
+    if points.is_empty() || total <= 0.0 {
+        return dashes;
+    }
+
+    // find where in the pattern the path starts, a zero length dash right at
+    // the start being kept as a dot
+    let mut index = 0usize;
+    let mut remaining = pattern[0];
+    let mut phase = offset.rem_euclid(total);
+    while phase >= remaining && !(phase == 0.0 && index.is_multiple_of(2)) {
+        phase -= remaining;
+        index = (index + 1) % pattern.len();
+        remaining = pattern[index];
+    }
+    remaining -= phase;
+
+    let mut current: Vec<Point> = Vec::new();
+    if index.is_multiple_of(2) {
+        current.push(points[0]);
+    }
+
+    for (from, to) in segments(points, closed) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 {
+            continue;
+        }
+
+        // walk the segment, switching between dash and gap on the way
+        let mut position = 0.0;
+        while remaining < length - position {
+            position += remaining;
+            let t = position / length;
+            let point = (from.0 + dx * t, from.1 + dy * t);
+            if index.is_multiple_of(2) {
+                current.push(point);
+                dashes.push(core::mem::take(&mut current));
+            } else {
+                current.push(point);
+            }
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+        remaining -= length - position;
+
+        if index.is_multiple_of(2) {
+            current.push(to);
+        }
+    }
+
+    if index.is_multiple_of(2) && !current.is_empty() {
+        dashes.push(current);
+    }
+    dashes
+}
+
+/// Iterates over the consecutive pairs of points of the path
+fn segments(points: &[Point], closed: bool) -> impl Iterator<Item = (Point, Point)> + '_ {
+    let closing = if closed && points.len() > 2 {
+        Some((points[points.len() - 1], points[0]))
+    } else {
+        None
+    };
+    points.windows(2).map(|w| (w[0], w[1])).chain(closing)
+}
+
+/// Builds the polygons covering a thick stroke of the path.
+///
+/// All polygons are wound the same way, so filling them together with the
+/// non-zero rule gives their union.
+fn stroke_polygons(points: &[Point], closed: bool, stroke: &Stroke) -> Vec<Vec<Point>> {
+    let half_width = stroke.width / 2.0;
+    let mut polygons = Vec::new();
+
+    // repeated points carry no direction
+    let mut path: Vec<Point> = Vec::with_capacity(points.len());
+    for &p in points {
+        if path.last() != Some(&p) {
+            path.push(p);
+        }
+    }
+    if closed && path.len() > 2 && path.first() == path.last() {
+        path.pop();
+    }
+
+    if path.len() == 1 {
+        // a lone point is only visible through its caps
+        let p = path[0];
+        match stroke.cap {
+            LineCap::Butt => {}
+            LineCap::Round => polygons.push(circle(p, half_width)),
+            LineCap::Square => polygons.push(cap_square(p, (1.0, 0.0), half_width, true)),
+        }
+        return polygons;
+    }
+
+    let closed = closed && path.len() > 2;
+    let directions: Vec<Point> = segments(&path, closed)
+        .map(|(from, to)| direction(from, to))
+        .collect();
+
+    for ((from, to), &d) in segments(&path, closed).zip(directions.iter()) {
+        polygons.push(segment_quad(from, to, d, half_width));
+    }
+
+    // joins between consecutive segments, wrapping around if closed
+    let count = directions.len();
+    let joins = if closed { count } else { count - 1 };
+    for i in 0..joins {
+        let joint = path[(i + 1) % path.len()];
+        let (d0, d1) = (directions[i], directions[(i + 1) % count]);
+        if let Some(polygon) = join(joint, d0, d1, half_width, stroke) {
+            polygons.push(polygon);
+        }
+    }
+
+    if !closed {
+        let (start, end) = (path[0], path[path.len() - 1]);
+        let (d_start, d_end) = (directions[0], directions[count - 1]);
+        match stroke.cap {
+            LineCap::Butt => {}
+            LineCap::Round => {
+                polygons.push(circle(start, half_width));
+                polygons.push(circle(end, half_width));
+            }
+            LineCap::Square => {
+                polygons.push(cap_square(start, d_start, half_width, false));
+                polygons.push(cap_square(end, d_end, half_width, true));
+            }
+        }
+    }
+
+    for polygon in polygons.iter_mut() {
+        orient(polygon);
+    }
+    polygons
+}
+
+/// Unit direction from one point to another, the points must differ
+#[inline]
+fn direction(from: Point, to: Point) -> Point {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    (dx / length, dy / length)
+}
+
+/// Quad covering a segment of the stroke, `d` being its unit direction
+fn segment_quad(from: Point, to: Point, d: Point, half_width: f32) -> Vec<Point> {
+    // normal of the segment, scaled to half the stroke width
+    let (nx, ny) = (-d.1 * half_width, d.0 * half_width);
+
+    vec![
+        (from.0 + nx, from.1 + ny),
+        (to.0 + nx, to.1 + ny),
+        (to.0 - nx, to.1 - ny),
+        (from.0 - nx, from.1 - ny),
+    ]
+}
+
+/// Polygon filling the gap on the outer side of the corner at `p`, where a
+/// segment along `d0` is followed by one along `d1`
+fn join(p: Point, d0: Point, d1: Point, half_width: f32, stroke: &Stroke) -> Option<Vec<Point>> {
+    let cross = d0.0 * d1.1 - d0.1 * d1.0;
+    let dot = d0.0 * d1.0 + d0.1 * d1.1;
+    // segments continue straight on, the quads already meet
+    if cross.abs() < 1e-6 && dot > 0.0 {
+        return None;
+    }
+
+    if stroke.join == LineJoin::Round {
+        return Some(circle(p, half_width));
+    }
+
+    // outer side of the corner is the one the path turns away from
+    let side = if cross > 0.0 { -half_width } else { half_width };
+    let o0 = (p.0 - d0.1 * side, p.1 + d0.0 * side);
+    let o1 = (p.0 - d1.1 * side, p.1 + d1.0 * side);
+
+    if stroke.join == LineJoin::Miter {
+        // miter length over line width is 1 / cos(turn / 2)
+        let cos_half_turn = ((1.0 + dot) / 2.0).max(0.0).sqrt();
+        if cos_half_turn > 0.0 && 1.0 / cos_half_turn <= stroke.miter_limit {
+            let (mx, my) = (o0.0 + o1.0 - 2.0 * p.0, o0.1 + o1.1 - 2.0 * p.1);
+            let m_length = (mx * mx + my * my).sqrt();
+            let tip_length = half_width / cos_half_turn;
+            let tip = (
+                p.0 + mx / m_length * tip_length,
+                p.1 + my / m_length * tip_length,
+            );
+            return Some(vec![p, o0, tip, o1]);
+        }
+    }
+
+    Some(vec![p, o0, o1])
+}
+
+/// Square extending the stroke by half its width past `p`, forwards along
+/// `d` at the end of a path or backwards at its start
+fn cap_square(p: Point, d: Point, half_width: f32, forward: bool) -> Vec<Point> {
+    let sign = if forward { 1.0 } else { -1.0 };
+    let (ex, ey) = (d.0 * half_width * sign, d.1 * half_width * sign);
+    let (nx, ny) = (-d.1 * half_width, d.0 * half_width);
+
+    vec![
+        (p.0 + nx, p.1 + ny),
+        (p.0 + nx + ex, p.1 + ny + ey),
+        (p.0 - nx + ex, p.1 - ny + ey),
+        (p.0 - nx, p.1 - ny),
+    ]
+}
+
+/// Polygon approximating the circle of the given radius, with segments
+/// short enough to be indistinguishable from the circle at pixel scale
+fn circle(center: Point, radius: f32) -> Vec<Point> {
+    let count = (radius * 4.0).ceil().clamp(8.0, 256.0) as usize;
+    (0..count)
+        .map(|i| {
+            let angle = i as f32 / count as f32 * core::f32::consts::PI * 2.0;
+            (
+                center.0 + radius * angle.cos(),
+                center.1 + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+/// Reverses the polygon if needed, so that it has a positive signed area
+fn orient(polygon: &mut [Point]) {
+    let n = polygon.len();
+    let area: f32 = (0..n)
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum();
+    if area < 0.0 {
+        polygon.reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::shape::{draw_line2d, draw_polyline2d, draw_quadratic_bezier2d, draw_rect2d};
+
+    const WIDTH: usize = 40;
+    const HEIGHT: usize = 40;
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const EMPTY: [u8; 4] = [0, 0, 0, 0];
+
+    #[test]
+    fn test_miter_join_fills_corner() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_line_width(6.0);
+
+        draw_rect2d(10, 10, 20, 20, &mut canvas, &color::WHITE);
+
+        // outer corners of the rectangle are square
+        assert_eq!(canvas.get_color(7, 7), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(32, 32), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(6, 6), Some(Color::from(EMPTY)));
+        assert_eq!(canvas.get_color(13, 13), Some(Color::from(EMPTY)));
+    }
+
+    #[test]
+    fn test_bevel_and_round_joins() {
+        let xi = [5, 30, 30];
+        let yi = [10, 10, 35];
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_line_width(8.0);
+        canvas.set_line_join(LineJoin::Bevel);
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+        // the bevel cuts off the outer corner
+        assert_eq!(canvas.get_color(31, 8), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(33, 7), Some(Color::from(EMPTY)));
+
+        canvas.clear(&Color::new(0, 0, 0, 0));
+        canvas.set_line_join(LineJoin::Miter);
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(33, 6), Some(Color::from(WHITE)));
+
+        canvas.clear(&Color::new(0, 0, 0, 0));
+        canvas.set_line_join(LineJoin::Round);
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(32, 7), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(33, 6), Some(Color::from(EMPTY)));
+    }
+
+    #[test]
+    fn test_miter_limit() {
+        // sharp spike, miter ratio is about 15
+        let xi = [5, 35, 5];
+        let yi = [18, 20, 22];
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_line_width(2.0);
+        canvas.set_miter_limit(16.0);
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(38, 20), Some(Color::from(WHITE)));
+
+        canvas.clear(&Color::new(0, 0, 0, 0));
+        canvas.set_miter_limit(10.0);
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(38, 20), Some(Color::from(EMPTY)));
+        assert_eq!(canvas.get_color(34, 20), Some(Color::from(WHITE)));
+    }
+
+    #[test]
+    fn test_line_caps() {
+        let xi = [10, 30];
+        let yi = [20, 20];
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_line_width(6.0);
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(10, 20), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(9, 20), Some(Color::from(EMPTY)));
+
+        canvas.clear(&Color::new(0, 0, 0, 0));
+        canvas.set_line_cap(LineCap::Square);
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(7, 17), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(32, 22), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(6, 20), Some(Color::from(EMPTY)));
+
+        canvas.clear(&Color::new(0, 0, 0, 0));
+        canvas.set_line_cap(LineCap::Round);
+        draw_polyline2d(&xi, &yi, &mut canvas, &color::WHITE);
+        assert_eq!(canvas.get_color(8, 20), Some(Color::from(WHITE)));
+        assert_eq!(canvas.get_color(7, 17), Some(Color::from(EMPTY)));
+    }
+
+    #[test]
+    fn test_dash_continues_across_segments() {
+        let points = [(0.0, 0.0), (6.0, 0.0), (6.0, 10.0)];
+
+        let dashes = dash_polyline(&points, false, &[4.0, 4.0], 0.0);
+        assert_eq!(
+            dashes,
+            vec![vec![(0.0, 0.0), (4.0, 0.0)], vec![(6.0, 2.0), (6.0, 6.0)]]
+        );
+
+        let dashes = dash_polyline(&points, false, &[4.0, 4.0], 2.0);
+        assert_eq!(
+            dashes,
+            vec![
+                vec![(0.0, 0.0), (2.0, 0.0)],
+                vec![(6.0, 0.0), (6.0, 4.0)],
+                vec![(6.0, 8.0), (6.0, 10.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dotted_line_starts_with_dot() {
+        let points = [(0.0, 0.0), (10.0, 0.0)];
+        let dashes = dash_polyline(&points, false, &[0.0, 4.0], 0.0);
+        assert_eq!(dashes[0], vec![(0.0, 0.0), (0.0, 0.0)]);
+        assert_eq!(dashes[1], vec![(4.0, 0.0), (4.0, 0.0)]);
+        assert_eq!(dashes.len(), 3);
+
+        // an offset ending a dash starts in the gap after it
+        let dashes = dash_polyline(&points, false, &[4.0, 4.0], 4.0);
+        assert_eq!(dashes, vec![vec![(4.0, 0.0), (8.0, 0.0)]]);
+
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_line_width(4.0);
+        canvas.set_line_cap(LineCap::Round);
+        canvas.set_line_dash(&[0.0, 8.0]);
+
+        draw_line2d(10, 20, 30, 20, &mut canvas, &color::WHITE);
+
+        // round dots of radius 2 centered on the start and every 8 pixels
+        for &x in [9, 10, 11, 17, 18, 19].iter() {
+            assert_eq!(canvas.get_color(x, 20), Some(Color::from(WHITE)));
+        }
+        for &x in [7, 13, 14, 15].iter() {
+            assert_eq!(canvas.get_color(x, 20), Some(Color::from(EMPTY)));
+        }
+    }
+
+    #[test]
+    fn test_dashed_line() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+        canvas.set_line_dash(&[3.0]);
+
+        draw_line2d(0, 5, 20, 5, &mut canvas, &color::WHITE);
+
+        // thin dashes include the pixels at both of their ends
+        for x in 0..=20 {
+            let expected = if x % 6 <= 3 { WHITE } else { EMPTY };
+            assert_eq!(canvas.get_color(x, 5), Some(Color::from(expected)));
+        }
+    }
+
+    #[test]
+    fn test_dashed_bezier_matches_line() {
+        let dashed_canvas = || {
+            let mut canvas = Canvas::new(WIDTH, HEIGHT).unwrap();
+            canvas.set_line_width(3.0);
+            canvas.set_line_dash(&[5.0, 3.0]);
+            canvas.set_line_dash_offset(0.5);
+            canvas
+        };
+
+        let mut line = dashed_canvas();
+        draw_line2d(2, 20, 32, 20, &mut line, &color::WHITE);
+
+        // a straight curve flattened into many segments, the dashes must not
+        // restart at each of them
+        let mut curve = dashed_canvas();
+        draw_quadratic_bezier2d((2, 20), (32, 20), (17, 20), &mut curve, &color::WHITE);
+
+        assert_eq!(line.bytes(), curve.bytes());
+        assert_eq!(line.get_color(6, 21), Some(Color::from(WHITE)));
+        assert_eq!(line.get_color(7, 21), Some(Color::from(EMPTY)));
+        assert_eq!(line.get_color(9, 21), Some(Color::from(EMPTY)));
+        assert_eq!(line.get_color(10, 21), Some(Color::from(WHITE)));
+    }
+}
@@ -0,0 +1,767 @@
+use super::path::{parse_path, Parser};
+use crate::errors::{invalid_svg_error, Error};
+use crate::shape::{
+    Ellipse2D, FillRule, Line2D, Path2D, Polygon2D, PolygonMode, Rectangle2D, Shape,
+};
+use crate::transform::Transform;
+use crate::{Canvas, Color, Context, Result};
+
+/// Attribute of a tag, with the byte offset of its value in the document
+type Attribute<'a> = (&'a str, &'a str, usize);
+
+/// Geometry of a drawable element, using the shape types of the crate. Shapes
+/// with fractional coordinates, which the integer based shapes can't hold,
+/// are kept as paths.
+#[derive(Debug, Clone)]
+enum Geometry {
+    Rect(Rectangle2D),
+    Ellipse(Ellipse2D),
+    Line(Line2D),
+    Polygon(Vec<i32>, Vec<i32>, PolygonMode),
+    Path(Path2D),
+}
+
+/// Presentation attributes, inherited by the children of a group
+#[derive(Debug, Copy, Clone)]
+struct Style {
+    transform: Transform,
+    fill: Option<Color>,
+    fill_rule: FillRule,
+    stroke: Option<Color>,
+    stroke_width: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            transform: Transform::identity(),
+            fill: Some(Color::rgb(0, 0, 0)),
+            fill_rule: FillRule::NonZero,
+            stroke: None,
+            stroke_width: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    geometry: Geometry,
+    style: Style,
+}
+
+/// A loaded SVG document, which can be rendered into a [`Canvas`].
+///
+/// Only a subset of SVG is supported: the `rect`, `circle`, `ellipse`,
+/// `line`, `polyline`, `polygon` and `path` elements, nested in `g` groups,
+/// along with the `transform`, `fill`, `fill-rule`, `stroke` and
+/// `stroke-width` attributes. Colors are given by name, as `#rgb`, `#rrggbb`
+/// or `rgb(r, g, b)`, `currentColor` being black. Other elements, like
+/// `defs` or `text`, are skipped with their content, and other attributes,
+/// including `style`, are ignored.
+///
+/// ```ignore
+/// let document = svg::Document::parse(&std::fs::read_to_string("icon.svg")?)?;
+/// let mut canvas = Canvas::new(document.width() as usize, document.height() as usize)?;
+/// document.render(&mut canvas);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Document {
+    width: f32,
+    height: f32,
+    elements: Vec<Element>,
+}
+
+impl Document {
+    /// Loads the document from its XML text. Malformed or unsupported
+    /// content is reported as
+    /// [`Error::InvalidSvg`](crate::errors::Error::InvalidSvg).
+    pub fn parse(data: &str) -> Result<Document> {
+        let mut document = Document {
+            width: 0.0,
+            height: 0.0,
+            elements: Vec::new(),
+        };
+        // open elements, with the style of the groups whose children are
+        // drawn and `None` for skipped ones
+        let mut open: Vec<(&str, Option<Style>)> = Vec::new();
+        let mut has_root = false;
+
+        let mut tags = Tags::new(data);
+        while let Some(tag) = tags.next_tag()? {
+            match tag {
+                Tag::Close(name, position) => match open.pop() {
+                    Some((open_name, _)) if open_name == name => {}
+                    _ => return invalid_svg_error(position, "mismatched closing tag"),
+                },
+                Tag::Open {
+                    name,
+                    attributes,
+                    self_closing,
+                    position,
+                } => {
+                    let children = match open.last() {
+                        None if has_root => {
+                            return invalid_svg_error(position, "content after the svg element")
+                        }
+                        None if name != "svg" => {
+                            return invalid_svg_error(position, "expected an svg element")
+                        }
+                        None => {
+                            has_root = true;
+                            document.width = length(&attributes, "width")?.unwrap_or(0.0);
+                            document.height = length(&attributes, "height")?.unwrap_or(0.0);
+                            Some(style(Style::default(), &attributes)?)
+                        }
+                        Some((_, None)) => None,
+                        Some((_, Some(parent))) => {
+                            let style = style(*parent, &attributes)?;
+                            match name {
+                                "g" | "svg" => Some(style),
+                                _ => {
+                                    if let Some(geometry) = geometry(name, &attributes, &style)? {
+                                        document.elements.push(Element { geometry, style });
+                                    }
+                                    None
+                                }
+                            }
+                        }
+                    };
+                    if !self_closing {
+                        open.push((name, children));
+                    }
+                }
+            }
+        }
+
+        if !has_root {
+            return invalid_svg_error(data.len(), "expected an svg element");
+        }
+        if !open.is_empty() {
+            return invalid_svg_error(data.len(), "unclosed element");
+        }
+        Ok(document)
+    }
+
+    /// Width of the document, from the `width` attribute of the `svg`
+    /// element or 0 if it has none
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Height of the document, from the `height` attribute of the `svg`
+    /// element or 0 if it has none
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    /// Draws the elements of the document in order, through the transform
+    /// and viewport of the canvas. Stroke widths are scaled along with the
+    /// shapes.
+    pub fn render(&self, canvas: &mut Canvas) {
+        let (viewport_scale, _) = canvas.viewport();
+        let mut ctx = Context::new(canvas);
+        let base = ctx.transform();
+
+        for element in self.elements.iter() {
+            let style = &element.style;
+            let transform = base.compose(&style.transform);
+            ctx.set_transform(transform);
+
+            // widths scale with the square root of the area scale
+            let det = (transform.a * transform.d - transform.b * transform.c)
+                * viewport_scale.0
+                * viewport_scale.1;
+            let stroke_width = style.stroke_width * det.abs().sqrt();
+
+            // lines enclose no area, filling would draw them
+            let fill = match element.geometry {
+                Geometry::Line(_) => None,
+                _ => style.fill,
+            };
+
+            element.with_shape(|shape| {
+                if let Some(fill) = fill {
+                    ctx.set_fill_style(fill);
+                    ctx.fill(shape);
+                }
+                if let Some(stroke) = style.stroke.filter(|_| stroke_width > 0.0) {
+                    ctx.set_stroke_style(stroke);
+                    ctx.set_line_width(stroke_width);
+                    ctx.stroke(shape);
+                }
+            });
+        }
+    }
+}
+
+impl Element {
+    /// Calls `f` with the shape of the element
+    fn with_shape<F: FnOnce(&dyn Shape)>(&self, f: F) {
+        match &self.geometry {
+            Geometry::Rect(rect) => f(rect),
+            Geometry::Ellipse(ellipse) => f(ellipse),
+            Geometry::Line(line) => f(line),
+            Geometry::Polygon(xi, yi, mode) => f(&Polygon2D::new(xi, yi, false)
+                .with_mode(*mode)
+                .with_fill_rule(self.style.fill_rule)),
+            Geometry::Path(path) => f(path),
+        }
+    }
+}
+
+/// Value of the attribute with the given name
+fn attribute<'a>(attributes: &[Attribute<'a>], name: &str) -> Option<(&'a str, usize)> {
+    attributes
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|&(_, value, position)| (value, position))
+}
+
+/// Turns errors of the path and number parser into errors of the document,
+/// `offset` being the position of the parsed value
+fn in_document(error: Error, offset: usize) -> Error {
+    match error {
+        Error::InvalidPath { position, reason } => Error::InvalidSvg {
+            position: offset + position,
+            reason,
+        },
+        other => other,
+    }
+}
+
+/// Numbers of a list separated by whitespace or commas
+fn numbers(value: &str, offset: usize) -> Result<Vec<f32>> {
+    let mut parser = Parser::new(value);
+    let mut numbers = Vec::new();
+    loop {
+        parser.skip_separators();
+        if parser.at_end() {
+            return Ok(numbers);
+        }
+        numbers.push(parser.number().map_err(|e| in_document(e, offset))?);
+    }
+}
+
+/// Length given by the attribute, in user units. Only unitless and `px`
+/// lengths are supported.
+fn length(attributes: &[Attribute], name: &str) -> Result<Option<f32>> {
+    let (value, position) = match attribute(attributes, name) {
+        Some(attribute) => attribute,
+        None => return Ok(None),
+    };
+    let trimmed = value.trim();
+    let number = trimmed.strip_suffix("px").unwrap_or(trimmed);
+    let mut parser = Parser::new(number);
+    let length = parser.number().map_err(|e| in_document(e, position))?;
+    parser.skip_separators();
+    if !parser.at_end() {
+        return invalid_svg_error(position, "unsupported length");
+    }
+
+    Ok(Some(length))
+}
+
+/// Style of an element, inheriting from `parent` the attributes it doesn't
+/// set
+fn style(parent: Style, attributes: &[Attribute]) -> Result<Style> {
+    let mut style = parent;
+    if let Some((value, position)) = attribute(attributes, "transform") {
+        style.transform = parent.transform.compose(&transform(value, position)?);
+    }
+    if let Some((value, position)) = attribute(attributes, "fill") {
+        style.fill = color(value, position)?;
+    }
+    if let Some((value, position)) = attribute(attributes, "stroke") {
+        style.stroke = color(value, position)?;
+    }
+    if let Some(width) = length(attributes, "stroke-width")? {
+        style.stroke_width = width;
+    }
+    if let Some((value, position)) = attribute(attributes, "fill-rule") {
+        style.fill_rule = match value.trim() {
+            "nonzero" => FillRule::NonZero,
+            "evenodd" => FillRule::EvenOdd,
+            _ => return invalid_svg_error(position, "unknown fill rule"),
+        };
+    }
+
+    Ok(style)
+}
+
+/// Parses a list of transform functions, like `translate(10) rotate(45)`,
+/// the rightmost one being applied to the points first
+fn transform(value: &str, offset: usize) -> Result<Transform> {
+    let mut result = Transform::identity();
+    let mut parser = Parser::new(value);
+    loop {
+        parser.skip_separators();
+        if parser.at_end() {
+            return Ok(result);
+        }
+
+        let start = parser.position;
+        while parser.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            parser.position += 1;
+        }
+        let name = &value[start..parser.position];
+        while parser.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            parser.position += 1;
+        }
+        if parser.peek() != Some(b'(') {
+            return invalid_svg_error(offset + parser.position, "expected a transform function");
+        }
+        parser.position += 1;
+
+        let mut args = Vec::new();
+        loop {
+            parser.skip_separators();
+            match parser.peek() {
+                Some(b')') => break,
+                None => return invalid_svg_error(offset + parser.position, "expected )"),
+                _ => args.push(parser.number().map_err(|e| in_document(e, offset))?),
+            }
+        }
+        parser.position += 1;
+
+        let transform = match (name, args.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Transform::new(a, b, c, d, e, f),
+            ("translate", &[tx]) => Transform::translation(tx, 0.0),
+            ("translate", &[tx, ty]) => Transform::translation(tx, ty),
+            ("scale", &[s]) => Transform::scaling(s, s),
+            ("scale", &[sx, sy]) => Transform::scaling(sx, sy),
+            ("rotate", &[angle]) => Transform::rotation(angle.to_radians()),
+            ("rotate", &[angle, cx, cy]) => Transform::translation(cx, cy)
+                .rotate(angle.to_radians())
+                .translate(-cx, -cy),
+            ("skewX", &[angle]) => Transform::skewing(angle.to_radians(), 0.0),
+            ("skewY", &[angle]) => Transform::skewing(0.0, angle.to_radians()),
+            ("matrix", _)
+            | ("translate", _)
+            | ("scale", _)
+            | ("rotate", _)
+            | ("skewX", _)
+            | ("skewY", _) => {
+                return invalid_svg_error(offset + start, "wrong number of transform arguments")
+            }
+            _ => return invalid_svg_error(offset + start, "unknown transform function"),
+        };
+        result = result.compose(&transform);
+    }
+}
+
+/// Parses a paint color, `None` standing for `none`
+fn color(value: &str, position: usize) -> Result<Option<Color>> {
+    let value = value.trim();
+    let invalid = || invalid_svg_error(position, "unsupported color");
+
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Option<Vec<u8>> = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect();
+        return match digits.as_deref() {
+            Some(&[r, g, b]) => Ok(Some(Color::rgb(r * 17, g * 17, b * 17))),
+            Some(&[r1, r2, g1, g2, b1, b2]) => {
+                Ok(Some(Color::rgb(r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)))
+            }
+            _ => invalid(),
+        };
+    }
+    if let Some(args) = value
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels: Vec<Option<u8>> = args.split(',').map(|c| c.trim().parse().ok()).collect();
+        return match channels.as_slice() {
+            &[Some(r), Some(g), Some(b)] => Ok(Some(Color::rgb(r, g, b))),
+            _ => invalid(),
+        };
+    }
+
+    let (r, g, b) = match value {
+        "none" => return Ok(None),
+        "transparent" => return Ok(Some(Color::new(0, 0, 0, 0))),
+        "black" | "currentColor" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "red" => (255, 0, 0),
+        "lime" => (0, 255, 0),
+        "green" => (0, 128, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "cyan" | "aqua" => (0, 255, 255),
+        "magenta" | "fuchsia" => (255, 0, 255),
+        "gray" | "grey" => (128, 128, 128),
+        "silver" => (192, 192, 192),
+        "maroon" => (128, 0, 0),
+        "olive" => (128, 128, 0),
+        "navy" => (0, 0, 128),
+        "purple" => (128, 0, 128),
+        "teal" => (0, 128, 128),
+        "orange" => (255, 165, 0),
+        _ => return invalid(),
+    };
+    Ok(Some(Color::rgb(r, g, b)))
+}
+
+/// Coordinates as integers, if they all are whole numbers
+fn integers(values: &[f32]) -> Option<Vec<i32>> {
+    values
+        .iter()
+        .map(|&v| {
+            let whole = v.fract() == 0.0 && v.abs() <= i32::MAX as f32;
+            whole.then_some(v as i32)
+        })
+        .collect()
+}
+
+/// Geometry of the element with the given name, or `None` for elements
+/// which are not drawn
+fn geometry(name: &str, attributes: &[Attribute], style: &Style) -> Result<Option<Geometry>> {
+    let number = |name: &str| -> Result<f32> { Ok(length(attributes, name)?.unwrap_or(0.0)) };
+
+    let geometry = match name {
+        "rect" => {
+            let (x, y) = (number("x")?, number("y")?);
+            let (width, height) = (number("width")?, number("height")?);
+            if width <= 0.0 || height <= 0.0 {
+                return Ok(None);
+            }
+            match integers(&[x, y, width, height]).as_deref() {
+                Some(&[x, y, width, height]) => {
+                    Geometry::Rect(Rectangle2D::new(x, y, width, height, false))
+                }
+                _ => {
+                    let mut path = Path2D::new(false);
+                    path.move_to(x, y)
+                        .line_to(x + width, y)
+                        .line_to(x + width, y + height)
+                        .line_to(x, y + height)
+                        .close();
+                    Geometry::Path(path)
+                }
+            }
+        }
+        "circle" | "ellipse" => {
+            let (cx, cy) = (number("cx")?, number("cy")?);
+            let (rx, ry) = if name == "circle" {
+                (number("r")?, number("r")?)
+            } else {
+                (number("rx")?, number("ry")?)
+            };
+            if rx <= 0.0 || ry <= 0.0 {
+                return Ok(None);
+            }
+            match integers(&[cx, cy, rx, ry]).as_deref() {
+                Some(&[cx, cy, rx, ry]) => Geometry::Ellipse(Ellipse2D::new(cx, cy, rx, ry, false)),
+                _ => {
+                    let mut path = Path2D::new(false);
+                    path.move_to(cx + rx, cy);
+                    path.arc((cx, cy), (rx, ry), 0.0, 0.0, core::f32::consts::TAU);
+                    path.close();
+                    Geometry::Path(path)
+                }
+            }
+        }
+        "line" => {
+            // lines are only visible when stroked
+            if style.stroke.is_none() {
+                return Ok(None);
+            }
+            let (x1, y1) = (number("x1")?, number("y1")?);
+            let (x2, y2) = (number("x2")?, number("y2")?);
+            match integers(&[x1, y1, x2, y2]).as_deref() {
+                Some(&[x1, y1, x2, y2]) => Geometry::Line(Line2D::new(x1, y1, x2, y2)),
+                _ => {
+                    let mut path = Path2D::new(false);
+                    path.move_to(x1, y1).line_to(x2, y2);
+                    Geometry::Path(path)
+                }
+            }
+        }
+        "polyline" | "polygon" => {
+            let (value, position) = attribute(attributes, "points").unwrap_or(("", 0));
+            let coordinates = numbers(value, position)?;
+            if !coordinates.len().is_multiple_of(2) {
+                return invalid_svg_error(position, "odd number of coordinates");
+            }
+            let closed = name == "polygon";
+            match integers(&coordinates) {
+                Some(coordinates) => {
+                    let xi = coordinates.iter().step_by(2).copied().collect();
+                    let yi = coordinates.iter().skip(1).step_by(2).copied().collect();
+                    let mode = if closed {
+                        PolygonMode::Closed
+                    } else {
+                        PolygonMode::Open
+                    };
+                    Geometry::Polygon(xi, yi, mode)
+                }
+                None => {
+                    let mut path = Path2D::new(false);
+                    for point in coordinates.chunks(2) {
+                        path.line_to(point[0], point[1]);
+                    }
+                    if closed {
+                        path.close();
+                    }
+                    Geometry::Path(path)
+                }
+            }
+        }
+        "path" => {
+            let (value, position) = attribute(attributes, "d").unwrap_or(("", 0));
+            let path = parse_path(value).map_err(|e| in_document(e, position))?;
+            Geometry::Path(path.with_fill_rule(style.fill_rule))
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(geometry))
+}
+
+/// Tag of the document
+enum Tag<'a> {
+    Open {
+        name: &'a str,
+        attributes: Vec<Attribute<'a>>,
+        self_closing: bool,
+        position: usize,
+    },
+    Close(&'a str, usize),
+}
+
+/// Reads the tags of an XML document, skipping text, comments, processing
+/// instructions and declarations
+struct Tags<'a> {
+    data: &'a str,
+    position: usize,
+}
+
+impl<'a> Tags<'a> {
+    fn new(data: &'a str) -> Self {
+        Tags { data, position: 0 }
+    }
+
+    #[inline]
+    fn rest(&self) -> &'a str {
+        &self.data[self.position..]
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.data.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    /// Moves past the next occurrence of `end`
+    fn skip_past(&mut self, end: &str, reason: &'static str) -> Result<()> {
+        match self.rest().find(end) {
+            Some(offset) => {
+                self.position += offset + end.len();
+                Ok(())
+            }
+            None => invalid_svg_error(self.position, reason),
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || b"-_:.".contains(&c))
+        {
+            self.position += 1;
+        }
+        if self.position == start {
+            return invalid_svg_error(start, "expected a name");
+        }
+        Ok(&self.data[start..self.position])
+    }
+
+    fn next_tag(&mut self) -> Result<Option<Tag<'a>>> {
+        loop {
+            match self.rest().find('<') {
+                Some(offset) => self.position += offset,
+                None => return Ok(None),
+            }
+
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.skip_past("-->", "unterminated comment")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.skip_past("]]>", "unterminated character data")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>", "unterminated processing instruction")?;
+            } else if rest.starts_with("<!") {
+                self.skip_past(">", "unterminated declaration")?;
+            } else if rest.starts_with("</") {
+                let position = self.position;
+                self.position += 2;
+                let name = self.name()?;
+                self.skip_whitespace();
+                if self.peek() != Some(b'>') {
+                    return invalid_svg_error(self.position, "expected >");
+                }
+                self.position += 1;
+                return Ok(Some(Tag::Close(name, position)));
+            } else {
+                return self.open_tag().map(Some);
+            }
+        }
+    }
+
+    fn open_tag(&mut self) -> Result<Tag<'a>> {
+        let position = self.position;
+        self.position += 1;
+        let name = self.name()?;
+        let mut attributes = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'>') => {
+                    self.position += 1;
+                    break;
+                }
+                Some(b'/') if self.rest().starts_with("/>") => {
+                    self.position += 2;
+                    return Ok(Tag::Open {
+                        name,
+                        attributes,
+                        self_closing: true,
+                        position,
+                    });
+                }
+                None => return invalid_svg_error(position, "unterminated tag"),
+                _ => {}
+            }
+
+            let attribute = self.name()?;
+            self.skip_whitespace();
+            if self.peek() != Some(b'=') {
+                return invalid_svg_error(self.position, "expected =");
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let quote = match self.peek() {
+                Some(q) if q == b'"' || q == b'\'' => q as char,
+                _ => return invalid_svg_error(self.position, "expected a quoted value"),
+            };
+            let start = self.position + 1;
+            let end = match self.data[start..].find(quote) {
+                Some(length) => start + length,
+                None => return invalid_svg_error(self.position, "unterminated value"),
+            };
+            attributes.push((attribute, &self.data[start..end], start));
+            self.position = end + 1;
+        }
+
+        Ok(Tag::Open {
+            name,
+            attributes,
+            self_closing: false,
+            position,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+
+    fn error(data: &str) -> (usize, &'static str) {
+        match Document::parse(data) {
+            Err(Error::InvalidSvg { position, reason }) => (position, reason),
+            other => panic!("{:?} loaded as {:?}", data, other),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let document = Document::parse(
+            r##"<?xml version="1.0"?>
+            <!-- a small scene -->
+            <svg xmlns="http://www.w3.org/2000/svg" width="40" height="30px">
+                <title>scene</title>
+                <rect x="2" y="2" width="10" height="6" fill="red"/>
+                <g transform="translate(20, 0)" fill="#00f">
+                    <circle cx="5" cy="5" r="4"/>
+                    <polygon points="0,20 8,20 8,28" fill="rgb(0, 255, 0)"/>
+                </g>
+                <line x1="0" y1="15" x2="39" y2="15" stroke="white"/>
+                <defs><rect width="40" height="30"/></defs>
+            </svg>"##,
+        )
+        .unwrap();
+        assert_eq!((document.width(), document.height()), (40.0, 30.0));
+
+        let mut canvas = Canvas::new(40, 30).unwrap();
+        document.render(&mut canvas);
+        let empty = Some(Color::new(0, 0, 0, 0));
+        assert_eq!(canvas.get_color(5, 5), Some(color::RED));
+        assert_eq!(canvas.get_color(25, 5), Some(color::BLUE));
+        assert_eq!(canvas.get_color(27, 26), Some(color::GREEN));
+        assert_eq!(canvas.get_color(21, 26), empty);
+        assert_eq!(canvas.get_color(35, 15), Some(color::WHITE));
+        // the rectangle in defs is not drawn
+        assert_eq!(canvas.get_color(38, 28), empty);
+    }
+
+    #[test]
+    fn test_transform_and_stroke_width() {
+        let document = Document::parse(
+            r#"<svg><g transform="scale(2)">
+                <rect x="1.5" y="2" width="4" height="4" fill="none" stroke="red" stroke-width="2"/>
+            </g></svg>"#,
+        )
+        .unwrap();
+        let mut canvas = Canvas::new(20, 20).unwrap();
+        document.render(&mut canvas);
+
+        // fractional rectangle from (3, 4) to (11, 12) with a 4 pixel stroke
+        let empty = Some(Color::new(0, 0, 0, 0));
+        assert_eq!(canvas.get_color(1, 8), Some(color::RED));
+        assert_eq!(canvas.get_color(4, 8), Some(color::RED));
+        assert_eq!(canvas.get_color(7, 8), empty);
+        assert_eq!(canvas.get_color(12, 8), Some(color::RED));
+        assert_eq!(canvas.get_color(14, 8), empty);
+        assert_eq!(canvas.line_width(), 1.0);
+    }
+
+    #[test]
+    fn test_transform_list() {
+        let t = transform("translate(10 5) rotate(90, 1, 1) scale(2,3)", 0).unwrap();
+        let (x, y) = t.apply(1.0, 0.0);
+        assert!(
+            (x - 12.0).abs() < 1e-4 && (y - 7.0).abs() < 1e-4,
+            "{} {}",
+            x,
+            y
+        );
+
+        assert!(matches!(
+            transform("skewX(1 2)", 10),
+            Err(Error::InvalidSvg { position: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error("<g></g>"), (0, "expected an svg element"));
+        assert_eq!(error("<svg><g></svg>"), (8, "mismatched closing tag"));
+        assert_eq!(error("<svg><g>"), (8, "unclosed element"));
+        assert_eq!(error(r#"<svg fill="pink"/>"#), (11, "unsupported color"));
+        assert_eq!(
+            error(r#"<svg><path d="M 0 0 L x"/></svg>"#),
+            (22, "expected a number")
+        );
+        assert_eq!(error(r#"<svg width=10/>"#), (11, "expected a quoted value"));
+        assert_eq!(error("<svg/><svg/>"), (6, "content after the svg element"));
+    }
+}
@@ -0,0 +1,10 @@
+//! The `svg` module reads shapes from SVG path data and documents, and
+//! writes them back out as SVG.
+
+mod document;
+mod path;
+mod writer;
+
+pub use document::Document;
+pub use path::parse_path;
+pub use writer::Writer;
@@ -0,0 +1,431 @@
+use crate::errors::invalid_path_error;
+use crate::shape::Path2D;
+use crate::Result;
+
+type Point = (f32, f32);
+
+/// Parses the path data of an SVG `d` attribute into a [`Path2D`].
+///
+/// All the commands of the SVG path grammar are supported, in both their
+/// absolute and relative forms: moves, lines (`L`, `H`, `V`), cubic and
+/// quadratic curves along with their smooth variants (`C`, `S`, `Q`, `T`),
+/// elliptical arcs (`A`) and `Z`. Arcs are converted to cubic curves.
+///
+/// The returned path is not filled, use [`Path2D::fill`] or
+/// [`Path2D::stroke`] to draw it either way. Malformed data is reported as
+/// [`Error::InvalidPath`](crate::errors::Error::InvalidPath) with the byte
+/// offset where parsing failed.
+///
+/// ```ignore
+/// let heart = svg::parse_path("M 10,30 A 20,20 0,0,1 50,30 A 20,20 0,0,1 90,30 Q 90,60 50,90 Q 10,60 10,30 z")?;
+/// heart.fill(&mut canvas, &color::RED);
+/// ```
+pub fn parse_path(data: &str) -> Result<Path2D> {
+    let mut parser = Parser::new(data);
+    let mut path = Path2D::new(false);
+
+    let mut current: Point = (0.0, 0.0);
+    let mut subpath_start: Point = (0.0, 0.0);
+    // control points of the previous curve, reflected by the smooth curves
+    let mut last_cubic: Option<Point> = None;
+    let mut last_quad: Option<Point> = None;
+    let mut previous: Option<u8> = None;
+
+    loop {
+        parser.skip_separators();
+        if parser.at_end() {
+            break;
+        }
+
+        let position = parser.position;
+        let command = match parser.peek() {
+            Some(c) if c.is_ascii_alphabetic() => {
+                parser.position += 1;
+                c
+            }
+            // arguments repeat the previous command, extra pairs after a move
+            // being lines
+            _ => match previous {
+                Some(b'M') => b'L',
+                Some(b'm') => b'l',
+                Some(c) if c != b'Z' && c != b'z' => c,
+                _ => return invalid_path_error(position, "expected a command"),
+            },
+        };
+        if previous.is_none() && command != b'M' && command != b'm' {
+            return invalid_path_error(position, "path data must start with a move");
+        }
+
+        let relative = command.is_ascii_lowercase();
+        let origin = if relative { current } else { (0.0, 0.0) };
+        let point = |parser: &mut Parser| -> Result<Point> {
+            let x = parser.number()?;
+            let y = parser.number()?;
+            Ok((origin.0 + x, origin.1 + y))
+        };
+
+        let mut cubic = None;
+        let mut quad = None;
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                current = point(&mut parser)?;
+                subpath_start = current;
+                path.move_to(current.0, current.1);
+            }
+            b'L' => {
+                current = point(&mut parser)?;
+                path.line_to(current.0, current.1);
+            }
+            b'H' => {
+                current.0 = origin.0 + parser.number()?;
+                path.line_to(current.0, current.1);
+            }
+            b'V' => {
+                current.1 = origin.1 + parser.number()?;
+                path.line_to(current.0, current.1);
+            }
+            b'C' => {
+                let c1 = point(&mut parser)?;
+                let c2 = point(&mut parser)?;
+                current = point(&mut parser)?;
+                path.cubic_to(c1.0, c1.1, c2.0, c2.1, current.0, current.1);
+                cubic = Some(c2);
+            }
+            b'S' => {
+                let c1 = reflect(last_cubic, current);
+                let c2 = point(&mut parser)?;
+                current = point(&mut parser)?;
+                path.cubic_to(c1.0, c1.1, c2.0, c2.1, current.0, current.1);
+                cubic = Some(c2);
+            }
+            b'Q' => {
+                let c = point(&mut parser)?;
+                current = point(&mut parser)?;
+                path.quad_to(c.0, c.1, current.0, current.1);
+                quad = Some(c);
+            }
+            b'T' => {
+                let c = reflect(last_quad, current);
+                current = point(&mut parser)?;
+                path.quad_to(c.0, c.1, current.0, current.1);
+                quad = Some(c);
+            }
+            b'A' => {
+                let rx = parser.number()?;
+                let ry = parser.number()?;
+                let rotation = parser.number()?;
+                let large_arc = parser.flag()?;
+                let sweep = parser.flag()?;
+                let end = point(&mut parser)?;
+                arc(
+                    &mut path,
+                    current,
+                    (rx, ry),
+                    rotation,
+                    large_arc,
+                    sweep,
+                    end,
+                );
+                current = end;
+            }
+            b'Z' => {
+                path.close();
+                current = subpath_start;
+            }
+            _ => return invalid_path_error(position, "unknown command"),
+        }
+
+        last_cubic = cubic;
+        last_quad = quad;
+        previous = Some(command);
+    }
+
+    Ok(path)
+}
+
+/// Control point of a smooth curve, the reflection of the previous control
+/// point about the current point, or the current point itself if the
+/// previous command was not a curve of the same kind
+fn reflect(control: Option<Point>, current: Point) -> Point {
+    match control {
+        Some(c) => (2.0 * current.0 - c.0, 2.0 * current.1 - c.1),
+        None => current,
+    }
+}
+
+/// Adds the elliptical arc from `from` to `to` given in the SVG endpoint
+/// parameterization, converting it to a center parameterization as in the
+/// [implementation notes](https://www.w3.org/TR/SVG11/implnote.html#ArcImplementationNotes)
+/// of the SVG specification.
+fn arc(
+    path: &mut Path2D,
+    from: Point,
+    radii: Point,
+    rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: Point,
+) {
+    if from == to {
+        return;
+    }
+    let (mut rx, mut ry) = (radii.0.abs() as f64, radii.1.abs() as f64);
+    if rx == 0.0 || ry == 0.0 {
+        path.line_to(to.0, to.1);
+        return;
+    }
+
+    let phi = (rotation as f64).to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    let (dx, dy) = (
+        (from.0 as f64 - to.0 as f64) / 2.0,
+        (from.1 as f64 - to.1 as f64) / 2.0,
+    );
+    let x1 = cos_phi * dx + sin_phi * dy;
+    let y1 = -sin_phi * dx + cos_phi * dy;
+
+    // radii too small to reach the end point are scaled up
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let (rx2, ry2) = (rx * rx, ry * ry);
+    let numerator = rx2 * ry2 - rx2 * y1 * y1 - ry2 * x1 * x1;
+    let denominator = rx2 * y1 * y1 + ry2 * x1 * x1;
+    let mut coef = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+
+    let cx = cos_phi * cx1 - sin_phi * cy1 + (from.0 as f64 + to.0 as f64) / 2.0;
+    let cy = sin_phi * cx1 + cos_phi * cy1 + (from.1 as f64 + to.1 as f64) / 2.0;
+
+    let start = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let end = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx);
+    let mut delta = end - start;
+    if sweep && delta < 0.0 {
+        delta += core::f64::consts::TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= core::f64::consts::TAU;
+    }
+
+    path.arc(
+        (cx as f32, cy as f32),
+        (rx as f32, ry as f32),
+        phi as f32,
+        start as f32,
+        delta as f32,
+    );
+}
+
+/// Reads the numbers and flags of path data
+pub(super) struct Parser<'a> {
+    data: &'a [u8],
+    pub(super) position: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(super) fn new(data: &'a str) -> Self {
+        Parser {
+            data: data.as_bytes(),
+            position: 0,
+        }
+    }
+
+    #[inline]
+    pub(super) fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).copied()
+    }
+
+    #[inline]
+    pub(super) fn at_end(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    /// Skips the whitespace and commas separating the arguments
+    pub(super) fn skip_separators(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_ascii_whitespace() || c == b',' {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Skips the digits, returning how many there were
+    fn skip_digits(&mut self) -> usize {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    /// Reads a number, like `-12`, `.5` or `1.5e-3`. A number ends where
+    /// another one can't continue, so `0.5.5` and `1-2` are two numbers.
+    pub(super) fn number(&mut self) -> Result<f32> {
+        self.skip_separators();
+        let start = self.position;
+
+        if matches!(self.peek(), Some(b'+') | Some(b'-')) {
+            self.position += 1;
+        }
+        let mut digits = self.skip_digits();
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            digits += self.skip_digits();
+        }
+        if digits == 0 {
+            self.position = start;
+            return invalid_path_error(start, "expected a number");
+        }
+
+        // exponent, only if digits follow so a command can come next
+        if matches!(self.peek(), Some(b'e') | Some(b'E')) {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if matches!(self.peek(), Some(b'+') | Some(b'-')) {
+                self.position += 1;
+            }
+            if self.skip_digits() == 0 {
+                self.position = mantissa_end;
+            }
+        }
+
+        // only ascii characters were consumed
+        let text = core::str::from_utf8(&self.data[start..self.position]).unwrap_or("");
+        match text.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => invalid_path_error(start, "number is out of range"),
+        }
+    }
+
+    /// Reads an arc flag, a single `0` or `1` which needs no separator
+    /// after it
+    fn flag(&mut self) -> Result<bool> {
+        self.skip_separators();
+        match self.peek() {
+            Some(b'0') => {
+                self.position += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.position += 1;
+                Ok(true)
+            }
+            _ => invalid_path_error(self.position, "expected a flag, 0 or 1"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+    use crate::{color, Canvas, Color};
+
+    fn error_position(data: &str) -> (usize, &'static str) {
+        match parse_path(data) {
+            Err(Error::InvalidPath { position, reason }) => (position, reason),
+            other => panic!("{:?} parsed as {:?}", data, other),
+        }
+    }
+
+    #[test]
+    fn test_absolute_and_relative() {
+        let mut expected = Path2D::new(false);
+        expected
+            .move_to(10.0, 10.0)
+            .line_to(20.0, 10.0)
+            .line_to(20.0, 20.0)
+            .line_to(10.0, 20.0)
+            .cubic_to(10.0, 25.0, 15.0, 30.0, 20.0, 30.0)
+            .quad_to(25.0, 30.0, 25.0, 35.0)
+            .close()
+            .move_to(0.5, -3e2)
+            .line_to(1.5, -302.0);
+
+        let absolute =
+            parse_path("M10 10 L20,10 V20 H10 C10 25 15 30 20 30 Q25 30 25 35 Z M.5-3e2 1.5-302")
+                .unwrap();
+        let relative =
+            parse_path("m10,10 h10 v10 l-10,0 c0,5 5,10 10,10 q5,0 5,5 z m-9.5-310 1-2").unwrap();
+        assert_eq!(absolute, expected);
+        assert_eq!(relative, expected);
+    }
+
+    #[test]
+    fn test_smooth_curves() {
+        let mut expected = Path2D::new(false);
+        expected
+            .move_to(0.0, 0.0)
+            .cubic_to(0.0, 10.0, 10.0, 10.0, 10.0, 0.0)
+            .cubic_to(10.0, -10.0, 20.0, -10.0, 20.0, 0.0)
+            .quad_to(25.0, 10.0, 30.0, 0.0)
+            .quad_to(35.0, -10.0, 40.0, 0.0)
+            // no previous quadratic curve, the control point is the
+            // current point
+            .line_to(50.0, 0.0)
+            .quad_to(50.0, 0.0, 60.0, 0.0);
+
+        let path =
+            parse_path("M0 0 C0 10 10 10 10 0 S20-10 20 0 Q25 10 30 0 T40 0 L50 0 T60 0").unwrap();
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn test_arc() {
+        // half circle from (0, 10) to (20, 10) with compact flags, passing
+        // above its center with the sweep flag set
+        let path = parse_path("M0 10a10 10 0 0120 0").unwrap();
+        let end = path.current_point().unwrap();
+        assert!((end.0 - 20.0).abs() < 1e-3 && (end.1 - 10.0).abs() < 1e-3);
+
+        let mut canvas = Canvas::new(30, 30).unwrap();
+        canvas.draw(&path, &color::WHITE);
+        assert_eq!(canvas.get_color(10, 0), Some(color::WHITE));
+        assert_eq!(canvas.get_color(10, 20), Some(Color::new(0, 0, 0, 0)));
+
+        // radii too small are scaled up to reach the end point
+        let path = parse_path("M0 10 A1 1 0 0 0 20 10").unwrap();
+        let mut canvas = Canvas::new(30, 30).unwrap();
+        canvas.draw(&path, &color::WHITE);
+        assert_eq!(canvas.get_color(10, 20), Some(color::WHITE));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error_position("L 10 10"),
+            (0, "path data must start with a move")
+        );
+        assert_eq!(error_position("M 10 10 L 5"), (11, "expected a number"));
+        assert_eq!(error_position("M 10 10 K 5 5"), (8, "unknown command"));
+        assert_eq!(error_position("M 0 0 z 5"), (8, "expected a command"));
+        assert_eq!(
+            error_position("M 0 0 A 5 5 0 2 0 1 1"),
+            (14, "expected a flag, 0 or 1")
+        );
+        assert_eq!(
+            error_position("M 0 0 L 1e99 0"),
+            (8, "number is out of range")
+        );
+
+        let error = parse_path("M 1 x").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid path data at 4: expected a number"
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(parse_path("").unwrap().is_empty());
+        assert!(parse_path("  \n ").unwrap().is_empty());
+    }
+}
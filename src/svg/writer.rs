@@ -0,0 +1,314 @@
+use std::fmt::Write;
+
+use crate::shape::{FillRule, Path2D, Segment, Shape, Target};
+use crate::transform::Transform;
+use crate::Color;
+
+/// Writes shapes as the elements of an SVG document, giving a vector version
+/// of the scene drawn into a [`Canvas`](crate::Canvas).
+///
+/// Every shape becomes one element: a [`Line2D`](crate::shape::Line2D) a
+/// `<line>`, an [`Ellipse2D`](crate::shape::Ellipse2D) an `<ellipse>`,
+/// polygons a `<polygon>` or `<polyline>`, and beziers and paths a `<path>`.
+/// Coordinates are written as given, the way [`Document`](super::Document)
+/// reads them, so rendering the document draws the same scene again.
+///
+/// ```ignore
+/// let mut writer = svg::Writer::new(100, 100);
+/// writer.set_line_width(2.0);
+/// writer.draw(&Line2D::new(10, 10, 90, 50), &color::WHITE);
+/// std::fs::write("scene.svg", writer.finish())?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Writer {
+    width: usize,
+    height: usize,
+    line_width: f32,
+    transform: Transform,
+    body: String,
+}
+
+impl Writer {
+    /// Starts an empty document of `width x height` pixels
+    pub fn new(width: usize, height: usize) -> Self {
+        Writer {
+            width,
+            height,
+            line_width: 1.0,
+            transform: Transform::identity(),
+            body: String::new(),
+        }
+    }
+
+    /// Sets the width of the outlines drawn after it, one pixel by default
+    pub fn set_line_width(&mut self, width: f32) {
+        self.line_width = width.max(0.0);
+    }
+
+    /// Width of the outlines
+    pub fn line_width(&self) -> f32 {
+        self.line_width
+    }
+
+    /// Sets the transform applied to the shapes drawn after it, written as
+    /// the `transform` attribute of their elements
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    /// Transform applied to the shapes
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Writes the shape, filled if it is a filled shape
+    pub fn draw(&mut self, shape: &dyn Shape, color: &Color) {
+        if shape.is_filled() {
+            shape.draw_filled(self, color)
+        } else {
+            shape.draw(self, color)
+        }
+    }
+
+    /// Returns the text of the document
+    pub fn finish(self) -> String {
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+            self.width, self.height
+        );
+        svg.push_str(&self.body);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Writes the element with its geometry given by `attributes`, followed
+    /// by the paint and transform
+    fn element(&mut self, name: &str, attributes: &str, filled: bool, color: &Color) {
+        let _ = write!(self.body, "<{} {}", name, attributes);
+        let hex = format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+        let paint = if filled {
+            let _ = write!(self.body, " fill=\"{}\"", hex);
+            "fill"
+        } else {
+            let _ = write!(
+                self.body,
+                " fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"",
+                hex, self.line_width
+            );
+            "stroke"
+        };
+        if color.a < 255 {
+            let opacity = (color.a as f32 / 255.0 * 1000.0).round() / 1000.0;
+            let _ = write!(self.body, " {}-opacity=\"{}\"", paint, opacity);
+        }
+        if !self.transform.is_identity() {
+            let t = &self.transform;
+            let _ = write!(
+                self.body,
+                " transform=\"matrix({} {} {} {} {} {})\"",
+                t.a, t.b, t.c, t.d, t.e, t.f
+            );
+        }
+        self.body.push_str("/>\n");
+    }
+}
+
+impl Target for Writer {
+    fn line(&mut self, from: (i32, i32), to: (i32, i32), color: &Color) {
+        let attributes = format!(
+            "x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+            from.0, from.1, to.0, to.1
+        );
+        self.element("line", &attributes, false, color);
+    }
+
+    fn rect(&mut self, x: i32, y: i32, width: i32, height: i32, filled: bool, color: &Color) {
+        let attributes = format!(
+            "x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"",
+            x, y, width, height
+        );
+        self.element("rect", &attributes, filled, color);
+    }
+
+    fn ellipse(&mut self, center: (i32, i32), radii: (i32, i32), filled: bool, color: &Color) {
+        let attributes = format!(
+            "cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\"",
+            center.0, center.1, radii.0, radii.1
+        );
+        self.element("ellipse", &attributes, filled, color);
+    }
+
+    fn polyline(&mut self, xi: &[i32], yi: &[i32], closed: bool, color: &Color) {
+        let name = if closed { "polygon" } else { "polyline" };
+        let attributes = format!("points=\"{}\"", points(xi, yi));
+        self.element(name, &attributes, false, color);
+    }
+
+    fn fill_polygon(&mut self, xi: &[i32], yi: &[i32], fill_rule: FillRule, color: &Color) {
+        let attributes = format!(
+            "points=\"{}\" fill-rule=\"{}\"",
+            points(xi, yi),
+            fill_rule_name(fill_rule)
+        );
+        self.element("polygon", &attributes, true, color);
+    }
+
+    fn quadratic_bezier(
+        &mut self,
+        start: (i32, i32),
+        control: (i32, i32),
+        end: (i32, i32),
+        color: &Color,
+    ) {
+        let attributes = format!(
+            "d=\"M{} {} Q{} {} {} {}\"",
+            start.0, start.1, control.0, control.1, end.0, end.1
+        );
+        self.element("path", &attributes, false, color);
+    }
+
+    fn cubic_bezier(
+        &mut self,
+        start: (i32, i32),
+        control_a: (i32, i32),
+        control_b: (i32, i32),
+        end: (i32, i32),
+        color: &Color,
+    ) {
+        let attributes = format!(
+            "d=\"M{} {} C{} {} {} {} {} {}\"",
+            start.0, start.1, control_a.0, control_a.1, control_b.0, control_b.1, end.0, end.1
+        );
+        self.element("path", &attributes, false, color);
+    }
+
+    fn path(&mut self, path: &Path2D, filled: bool, color: &Color) {
+        if path.is_empty() {
+            return;
+        }
+        let mut data = Vec::new();
+        for segment in path.segments() {
+            data.push(match *segment {
+                Segment::MoveTo((x, y)) => format!("M{} {}", x, y),
+                Segment::LineTo((x, y)) => format!("L{} {}", x, y),
+                Segment::QuadTo((cx, cy), (x, y)) => format!("Q{} {} {} {}", cx, cy, x, y),
+                Segment::CubicTo((ax, ay), (bx, by), (x, y)) => {
+                    format!("C{} {} {} {} {} {}", ax, ay, bx, by, x, y)
+                }
+                Segment::Close => "Z".to_string(),
+            });
+        }
+        let mut attributes = format!("d=\"{}\"", data.join(" "));
+        if filled {
+            let _ = write!(
+                attributes,
+                " fill-rule=\"{}\"",
+                fill_rule_name(path.fill_rule())
+            );
+        }
+        self.element("path", &attributes, filled, color);
+    }
+}
+
+/// Points in the format of the `points` attribute
+fn points(xi: &[i32], yi: &[i32]) -> String {
+    let points: Vec<String> = xi
+        .iter()
+        .zip(yi.iter())
+        .map(|(x, y)| format!("{},{}", x, y))
+        .collect();
+    points.join(" ")
+}
+
+fn fill_rule_name(fill_rule: FillRule) -> &'static str {
+    match fill_rule {
+        FillRule::NonZero => "nonzero",
+        FillRule::EvenOdd => "evenodd",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::shape::{CubicBezier2D, Ellipse2D, Line2D, Polygon2D, PolygonMode, Rectangle2D};
+    use crate::svg::Document;
+    use crate::Canvas;
+
+    #[test]
+    fn test_elements() {
+        let mut writer = Writer::new(40, 30);
+        writer.draw(&Line2D::new(1, 2, 30, 4), &color::WHITE);
+        writer.draw(
+            &Rectangle2D::new(5, 5, 10, 8, true),
+            &Color::new(255, 0, 0, 128),
+        );
+        writer.set_line_width(2.0);
+        writer.draw(&Ellipse2D::new(20, 15, 6, 4, false), &Color::rgb(0, 0, 255));
+        writer.draw(
+            &CubicBezier2D::new((0, 0), (30, 0), (10, 20), (20, 20)),
+            &color::WHITE,
+        );
+        let (xi, yi) = ([0, 10, 5], [0, 0, 8]);
+        writer.draw(
+            &Polygon2D::new(&xi, &yi, false).with_mode(PolygonMode::Open),
+            &color::WHITE,
+        );
+
+        let svg = writer.finish();
+        let lines: Vec<&str> = svg.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"40\" height=\"30\" viewBox=\"0 0 40 30\">",
+                "<line x1=\"1\" y1=\"2\" x2=\"30\" y2=\"4\" fill=\"none\" stroke=\"#ffffff\" stroke-width=\"1\"/>",
+                "<rect x=\"5\" y=\"5\" width=\"10\" height=\"8\" fill=\"#ff0000\" fill-opacity=\"0.502\"/>",
+                "<ellipse cx=\"20\" cy=\"15\" rx=\"6\" ry=\"4\" fill=\"none\" stroke=\"#0000ff\" stroke-width=\"2\"/>",
+                "<path d=\"M0 0 C10 20 20 20 30 0\" fill=\"none\" stroke=\"#ffffff\" stroke-width=\"2\"/>",
+                "<polyline points=\"0,0 10,0 5,8\" fill=\"none\" stroke=\"#ffffff\" stroke-width=\"2\"/>",
+                "</svg>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_and_transform() {
+        let mut path = Path2D::new(true).with_fill_rule(FillRule::EvenOdd);
+        path.move_to(0.0, 0.0)
+            .line_to(10.5, 0.0)
+            .quad_to(12.0, 5.0, 10.0, 10.0)
+            .close();
+        let mut writer = Writer::new(20, 20);
+        writer.set_transform(Transform::translation(2.0, 3.0));
+        writer.draw(&path, &color::WHITE);
+
+        let svg = writer.finish();
+        assert!(svg.contains(
+            "<path d=\"M0 0 L10.5 0 Q12 5 10 10 Z\" fill-rule=\"evenodd\" fill=\"#ffffff\" \
+             transform=\"matrix(1 0 0 1 2 3)\"/>"
+        ));
+    }
+
+    #[test]
+    fn test_document_matches_canvas() {
+        // the written document renders back to the scene drawn directly
+        let rect = Rectangle2D::new(3, 4, 12, 9, true);
+        let ellipse = Ellipse2D::new(20, 20, 7, 5, false);
+        let (xi, yi) = ([2, 28, 15], [28, 26, 12]);
+        let polygon = Polygon2D::new(&xi, &yi, true);
+
+        let mut writer = Writer::new(32, 32);
+        let mut expected = Canvas::new(32, 32).unwrap();
+        for shape in [&rect as &dyn Shape, &ellipse, &polygon] {
+            writer.draw(shape, &color::WHITE);
+            expected.draw(shape, &color::WHITE);
+        }
+
+        let document = Document::parse(&writer.finish()).unwrap();
+        let mut canvas = Canvas::new(32, 32).unwrap();
+        document.render(&mut canvas);
+        assert_eq!(canvas.bytes(), expected.bytes());
+    }
+}
@@ -0,0 +1,191 @@
+//! The `transform` module defines the affine transforms shapes can be drawn
+//! through.
+
+/// A 2D affine transform, mapping the point `(x, y)` to
+/// `(a * x + c * y + e, b * x + d * y + f)`.
+///
+/// The fields follow the HTML canvas and SVG `matrix(a, b, c, d, e, f)`
+/// notation. The methods building on an existing transform, like
+/// [`Transform::rotate`], apply the new operation to the points first, so
+/// `Transform::identity().translate(10.0, 0.0).rotate(angle)` rotates the
+/// points and then moves them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Transform { a, b, c, d, e, f }
+    }
+
+    /// Transform leaving the points as they are
+    pub fn identity() -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    /// Transform moving the points by `(tx, ty)`
+    pub fn translation(tx: f32, ty: f32) -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, tx, ty)
+    }
+
+    /// Transform rotating the points by `angle` radians around the origin.
+    /// With the y axis pointing down, positive angles turn clockwise.
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Transform::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// Transform scaling the points by `sx` and `sy` from the origin
+    pub fn scaling(sx: f32, sy: f32) -> Self {
+        Transform::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+    }
+
+    /// Transform slanting the x axis by `angle_y` radians and the y axis by
+    /// `angle_x` radians, as in the CSS `skew(angle_x, angle_y)` function
+    pub fn skewing(angle_x: f32, angle_y: f32) -> Self {
+        Transform::new(1.0, angle_y.tan(), angle_x.tan(), 1.0, 0.0, 0.0)
+    }
+
+    /// Moves the points by `(tx, ty)` before applying this transform
+    pub fn translate(self, tx: f32, ty: f32) -> Self {
+        self.compose(&Transform::translation(tx, ty))
+    }
+
+    /// Rotates the points by `angle` radians before applying this transform
+    pub fn rotate(self, angle: f32) -> Self {
+        self.compose(&Transform::rotation(angle))
+    }
+
+    /// Scales the points by `sx` and `sy` before applying this transform
+    pub fn scale(self, sx: f32, sy: f32) -> Self {
+        self.compose(&Transform::scaling(sx, sy))
+    }
+
+    /// Skews the points before applying this transform, see
+    /// [`Transform::skewing`]
+    pub fn skew(self, angle_x: f32, angle_y: f32) -> Self {
+        self.compose(&Transform::skewing(angle_x, angle_y))
+    }
+
+    /// Transform applying `other` to the points first and then `self`
+    pub fn compose(&self, other: &Transform) -> Self {
+        Transform::new(
+            self.a * other.a + self.c * other.b,
+            self.b * other.a + self.d * other.b,
+            self.a * other.c + self.c * other.d,
+            self.b * other.c + self.d * other.d,
+            self.a * other.e + self.c * other.f + self.e,
+            self.b * other.e + self.d * other.f + self.f,
+        )
+    }
+
+    /// Transform undoing this one, or `None` if it collapses the plane onto
+    /// a line or a point
+    pub fn invert(&self) -> Option<Self> {
+        let det = self.a as f64 * self.d as f64 - self.b as f64 * self.c as f64;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let (a, b, c, d) = (
+            self.d as f64 / det,
+            -self.b as f64 / det,
+            -self.c as f64 / det,
+            self.a as f64 / det,
+        );
+        let (e, f) = (self.e as f64, self.f as f64);
+        Some(Transform::new(
+            a as f32,
+            b as f32,
+            c as f32,
+            d as f32,
+            (-(a * e + c * f)) as f32,
+            (-(b * e + d * f)) as f32,
+        ))
+    }
+
+    /// Maps the point through the transform
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let (x, y) = self.apply_f64(x as f64, y as f64);
+        (x as f32, y as f32)
+    }
+
+    /// Same as [`Transform::apply`], in double precision so integer
+    /// coordinates stay exact
+    #[inline]
+    pub(crate) fn apply_f64(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a as f64 * x + self.c as f64 * y + self.e as f64,
+            self.b as f64 * x + self.d as f64 * y + self.f as f64,
+        )
+    }
+
+    /// Whether the transform leaves the points as they are
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::identity()
+    }
+
+    /// Whether the transform keeps horizontal lines horizontal and vertical
+    /// lines vertical, i.e. it only scales and translates
+    pub fn is_axis_aligned(&self) -> bool {
+        self.b == 0.0 && self.c == 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: (f32, f32), b: (f32, f32)) {
+        assert!(
+            (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_operations() {
+        let quarter = core::f32::consts::FRAC_PI_2;
+        assert_close(Transform::translation(3.0, 4.0).apply(1.0, 1.0), (4.0, 5.0));
+        assert_close(Transform::rotation(quarter).apply(1.0, 0.0), (0.0, 1.0));
+        assert_close(Transform::scaling(2.0, 3.0).apply(1.0, 1.0), (2.0, 3.0));
+        assert_close(
+            Transform::skewing(quarter / 2.0, 0.0).apply(0.0, 2.0),
+            (2.0, 2.0),
+        );
+
+        // points are rotated first, then moved
+        let t = Transform::identity().translate(10.0, 0.0).rotate(quarter);
+        assert_close(t.apply(1.0, 0.0), (10.0, 1.0));
+    }
+
+    #[test]
+    fn test_invert() {
+        let t = Transform::identity()
+            .translate(5.0, -2.0)
+            .rotate(0.3)
+            .scale(2.0, 0.5)
+            .skew(0.2, 0.1);
+        let inverse = t.invert().unwrap();
+
+        let (x, y) = t.apply(7.0, 3.0);
+        assert_close(inverse.apply(x, y), (7.0, 3.0));
+        assert_close(t.compose(&inverse).apply(-4.0, 9.0), (-4.0, 9.0));
+
+        assert_eq!(Transform::scaling(0.0, 1.0).invert(), None);
+    }
+}